@@ -0,0 +1,256 @@
+// Acceptance ratio experiments
+//
+// For a range of utilizations, random task sets are generated and checked by
+// each schedulability test (variant). The acceptance ratio is the fraction of
+// sets deemed schedulable. Sets are seeded by their index, so results do not
+// depend on the number of threads.
+
+use crate::common::*;
+use crate::generate::{GenConfig, Rng};
+use crate::simulator::Simulation;
+use plotters::prelude::*;
+use std::fmt::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    // response_time, the current interference model
+    Analysis,
+    // response_time_exact, interference by ceil(R / A)
+    Exact,
+    // no deadline miss in simulation
+    Simulation,
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Variant::Analysis => "analysis",
+            Variant::Exact => "exact",
+            Variant::Simulation => "simulation",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "analysis" => Ok(Variant::Analysis),
+            "exact" => Ok(Variant::Exact),
+            "simulation" => Ok(Variant::Simulation),
+            _ => Err(format!("unknown variant {}", s)),
+        }
+    }
+}
+
+impl Variant {
+    // Whether the variant deems tasks schedulable
+    pub fn accepts(&self, tasks: &Tasks) -> bool {
+        match self {
+            Variant::Analysis => tasks
+                .response_time()
+                .is_ok_and(|r| r.0.iter().all(|r| r.response_time.is_some())),
+            Variant::Exact => tasks
+                .response_time_exact()
+                .is_ok_and(|r| r.iter().all(|r| r.is_some())),
+            Variant::Simulation => simulate(tasks),
+        }
+    }
+}
+
+// No deadline miss at the synchronous release, nor when each task is
+// released as a lower priority task takes its worst blocking lock
+fn simulate(tasks: &Tasks) -> bool {
+    let sim = Simulation::new(tasks);
+    let start = tasks.0.iter().map(|t| t.inter_arrival).max().unwrap_or(0);
+    let horizon = 3 * start;
+    let no_miss = |offsets: &[Time]| {
+        sim.run(offsets, None, horizon)
+            .iter()
+            .all(|o| o.deadline_misses == 0)
+    };
+    no_miss(&[]) && (0..tasks.0.len()).all(|t| no_miss(&sim.blocking_offsets(t, start)))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Experiment {
+    pub generate: GenConfig,
+    pub utilizations: Vec<f64>,
+    // task sets per utilization
+    pub sets: usize,
+    pub seed: u64,
+    pub threads: usize,
+    pub variants: Vec<Variant>,
+}
+
+// The number of sets accepted by each variant, at a utilization
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub utilization: f64,
+    pub sets: usize,
+    pub accepted: Vec<usize>,
+}
+
+impl Experiment {
+    // The task set of index i at utilization u
+    pub fn task_set(&self, u: f64, i: usize) -> Result<Tasks, String> {
+        let config = GenConfig {
+            utilization: u,
+            ..self.generate.clone()
+        };
+        config.generate(&mut Rng::new(self.seed.wrapping_add(i as u64)))
+    }
+
+    // The rows of the experiment, or an error if its sets cannot be generated
+    pub fn run(&self) -> Result<Vec<Row>, String> {
+        self.generate.validate()?;
+        let total = self.utilizations.len() * self.sets;
+        let next = AtomicUsize::new(0);
+        let accepted: Vec<(usize, Vec<bool>)> = std::thread::scope(|s| {
+            let workers: Vec<_> = (0..self.threads.max(1))
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut done = vec![];
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            if i >= total {
+                                break done;
+                            }
+                            let tasks = self
+                                .task_set(self.utilizations[i / self.sets], i)
+                                .expect("config validated by run");
+                            done.push((
+                                i,
+                                self.variants.iter().map(|v| v.accepts(&tasks)).collect(),
+                            ));
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|w| w.join().unwrap())
+                .collect()
+        });
+
+        let mut rows: Vec<Row> = self
+            .utilizations
+            .iter()
+            .map(|u| Row {
+                utilization: *u,
+                sets: self.sets,
+                accepted: vec![0; self.variants.len()],
+            })
+            .collect();
+        for (i, accepts) in accepted {
+            for (v, accept) in accepts.into_iter().enumerate() {
+                if accept {
+                    rows[i / self.sets].accepted[v] += 1;
+                }
+            }
+        }
+        Ok(rows)
+    }
+}
+
+// Acceptance ratios as CSV, a column per variant
+pub fn csv(variants: &[Variant], rows: &[Row]) -> String {
+    let mut s = String::from("utilization,sets");
+    for v in variants {
+        write!(s, ",{}", v).unwrap();
+    }
+    writeln!(s).unwrap();
+    for r in rows {
+        write!(s, "{:.3},{}", r.utilization, r.sets).unwrap();
+        for a in &r.accepted {
+            write!(s, ",{:.3}", *a as f64 / r.sets.max(1) as f64).unwrap();
+        }
+        writeln!(s).unwrap();
+    }
+    s
+}
+
+// Acceptance ratios over utilization as an SVG chart, a line per variant
+pub fn chart(
+    path: &Path,
+    variants: &[Variant],
+    rows: &[Row],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let root = SVGBackend::new(path, (640, 480)).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let max_u = rows.iter().map(|r| r.utilization).fold(1.0, f64::max);
+    let mut chart = ChartBuilder::on(&root)
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .caption("Acceptance ratio", ("sans-serif", 24))
+        .build_cartesian_2d(0.0..max_u, 0.0..1.0)?;
+    chart
+        .configure_mesh()
+        .x_desc("Utilization")
+        .y_desc("Acceptance ratio")
+        .draw()?;
+
+    for (v, variant) in variants.iter().enumerate() {
+        let color = Palette99::pick(v).to_rgba();
+        chart
+            .draw_series(LineSeries::new(
+                rows.iter()
+                    .map(|r| (r.utilization, r.accepted[v] as f64 / r.sets.max(1) as f64)),
+                color,
+            ))?
+            .label(variant.to_string())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+    chart
+        .configure_series_labels()
+        .background_style(WHITE)
+        .border_style(BLACK)
+        .draw()?;
+    root.present()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn acceptance_ratio() {
+        let experiment = Experiment {
+            generate: GenConfig {
+                tasks: 4,
+                ..GenConfig::default()
+            },
+            utilizations: vec![0.2, 0.9],
+            sets: 10,
+            seed: 1,
+            threads: 2,
+            variants: vec![Variant::Analysis, Variant::Exact, Variant::Simulation],
+        };
+        let rows = experiment.run().unwrap();
+        assert_eq!(
+            rows,
+            Experiment {
+                threads: 1,
+                ..experiment.clone()
+            }
+            .run()
+            .unwrap()
+        );
+        for r in &rows {
+            // the exact test dominates the analysis, and simulation both
+            assert!(r.accepted[0] <= r.accepted[1]);
+            assert!(r.accepted[1] <= r.accepted[2]);
+        }
+        assert!(rows[0].accepted[0] >= rows[1].accepted[0]);
+        assert!(csv(&experiment.variants, &rows)
+            .starts_with("utilization,sets,analysis,exact,simulation\n0.200,10,"));
+    }
+}
@@ -1,222 +1,431 @@
 // SRP based analysis of task set
 
 use crate::common::*;
+use crate::model::{Model, MAX_ITERATIONS};
 use std::collections::HashMap;
 
 // A map from Task/Resource identifiers to priority
 pub type IdPrio = HashMap<String, u8>;
 
 // helper functions
-fn update_prio(prio: u8, trace: &Trace, hm: &mut IdPrio) {
-    if let Some(old_prio) = hm.get(&trace.id) {
-        if prio > *old_prio {
-            hm.insert(trace.id.clone(), prio);
+// The longest critical section per resource, with the path of trace ids leading to it
+fn update_critical_sections(
+    trace: &Trace,
+    path: &mut Vec<String>,
+    cs: &mut HashMap<String, (Time, Vec<String>)>,
+) {
+    path.push(trace.id.clone());
+    for inner in &trace.inner {
+        if inner.kind.is_section() {
+            let wcet = inner.wcet();
+            if cs.get(&inner.id).is_none_or(|(max, _)| wcet > *max) {
+                let mut path = path.clone();
+                path.push(inner.id.clone());
+                cs.insert(inner.id.clone(), (wcet, path));
+            }
         }
-    } else {
-        hm.insert(trace.id.clone(), prio);
-    }
-    for cs in &trace.inner {
-        update_prio(prio, cs, hm);
+        update_critical_sections(inner, path, cs);
     }
+    path.pop();
 }
 
 impl Trace {
-    pub fn wcet(&self) -> u32 {
-        self.end - self.start
+    // The measured duration of self
+    pub fn span(&self) -> Time {
+        self.end.saturating_sub(self.start)
     }
 
-    pub fn blocking(&self, t: &Task, ip: &IdPrio) -> u32 {
-        println!("blocking trace\n{}", self);
-        if let Some(p) = ip.get(&self.id) {
-            println!("block on {} at prio {}", self.id, p);
-            if *p >= t.prio {
-                println!("-- blocking -- {}", self.wcet());
-                return self.wcet();
-            }
+    // The wcet of self, taking the worst alternative and loop bounds into
+    // account, Time::MAX on overflow (rejected by Tasks::validate)
+    pub fn wcet(&self) -> Time {
+        self.checked_wcet().unwrap_or(Time::MAX)
+    }
+
+    // The wcet of self, None on overflow
+    pub fn checked_wcet(&self) -> Option<Time> {
+        match self.kind {
+            TraceKind::Alt => self
+                .inner
+                .iter()
+                .try_fold(0, |max: Time, t| Some(max.max(t.checked_wcet()?))),
+            TraceKind::Loop(bound) => self.body_wcet()?.checked_mul(bound as Time),
+            TraceKind::Section | TraceKind::Path => self.body_wcet(),
         }
+    }
 
-        self.inner.iter().fold(0, |blocking, trace| {
-            // println!("trace {}", trace);
-            blocking.max(trace.blocking(t, ip))
-        })
+    // The measured time spent in self outside inner nodes, plus the wcet of inner nodes
+    fn body_wcet(&self) -> Option<Time> {
+        let own = self
+            .inner
+            .iter()
+            .fold(self.span(), |own, t| own.saturating_sub(t.span()));
+        self.inner
+            .iter()
+            .try_fold(own, |wcet, t| wcet.checked_add(t.checked_wcet()?))
     }
-}
 
-impl Task {
-    // The wcet of self
-    pub fn wcet(&self) -> u32 {
-        self.trace.end - self.trace.start
+    // The first node of self (in pre-order) ending before it starts
+    pub fn invalid_span(&self) -> Option<&Trace> {
+        if self.end < self.start {
+            return Some(self);
+        }
+        self.inner.iter().find_map(|t| t.invalid_span())
     }
 
-    // The blocking of self to a task t
-    pub fn blocking(&self, t: &Task, ip: &IdPrio) -> u32 {
-        println!("check blocking of {} by {}", t.id, self.id);
-        self.trace.blocking(t, ip)
+    // The bcet of self, the annotated best case if given, otherwise taking the
+    // best alternative, and loops possibly not executing their inner nodes at all
+    pub fn bcet(&self) -> Time {
+        if let Some(best) = self.best {
+            return best;
+        }
+        match self.kind {
+            TraceKind::Alt => self.inner.iter().map(|t| t.bcet()).min().unwrap_or(0),
+            TraceKind::Loop(_) => 0,
+            TraceKind::Section | TraceKind::Path => self.body_bcet(),
+        }
     }
-}
 
-impl Tasks {
-    // Derives the above maps from a set of tasks
-    pub fn pre_analysis(&self) -> IdPrio {
-        let mut ip = HashMap::new();
+    // The measured time spent in self outside inner nodes, plus the bcet of inner nodes
+    fn body_bcet(&self) -> Time {
+        let own = self
+            .inner
+            .iter()
+            .fold(self.span(), |own, t| own.saturating_sub(t.span()));
+        self.inner
+            .iter()
+            .fold(own, |bcet, t| bcet.saturating_add(t.bcet()))
+    }
 
-        for t in &self.0 {
-            update_prio(t.prio, &t.trace, &mut ip);
+    // The first node of self (in pre-order) with a best case exceeding its wcet
+    pub fn invalid_best(&self) -> Option<&Trace> {
+        if self.best.is_some_and(|best| best > self.wcet()) {
+            return Some(self);
         }
+        self.inner.iter().find_map(|t| t.invalid_best())
+    }
+}
 
-        ip
+impl Task {
+    // All observed traces of self
+    pub fn all_traces(&self) -> impl Iterator<Item = &Trace> {
+        std::iter::once(&self.trace).chain(self.traces.iter())
     }
 
-    // The set of tasks with lower priority than t
-    pub fn lower(&self, t: &Task) -> Tasks {
-        Tasks(
-            self.0
-                .clone()
-                .into_iter()
-                .filter(|t1| t1.prio < t.prio)
-                .collect(),
-        )
+    // The wcet of self, over all observed traces
+    pub fn wcet(&self) -> Time {
+        self.all_traces()
+            .map(|trace| trace.wcet())
+            .max()
+            .unwrap_or(0)
     }
 
-    // The set of tasks with higher priority than t
-    pub fn higher(&self, t: &Task) -> Tasks {
-        Tasks(
-            self.0
-                .clone()
-                .into_iter()
-                .filter(|t1| t1.prio > t.prio)
-                .collect(),
-        )
+    // The wcet of self including the dispatch overhead, None on overflow
+    pub fn checked_wcet(&self) -> Option<Time> {
+        self.all_traces()
+            .try_fold(0, |max: Time, trace| Some(max.max(trace.checked_wcet()?)))?
+            .checked_add(self.overhead())
     }
 
-    // The set of tasks with higher or equal priority than t
-    pub fn higher_or_equal(&self, t: &Task) -> Tasks {
-        Tasks(
-            self.0
-                .clone()
-                .into_iter()
-                .filter(|t1| t1.prio == t.prio && t1.id != t.id)
-                .collect(),
-        )
+    // Whether self runs in the background, as idle at priority 0, without a deadline
+    pub fn is_background(&self) -> bool {
+        self.prio == 0
     }
 
-    // total utilization
-    pub fn total_utilization(&self) -> f32 {
-        let mut tot_util = 0.0;
+    // The dispatch overhead of self per job, 0 for a hardware task
+    pub fn overhead(&self) -> Time {
+        self.dispatch.as_ref().map_or(0, |d| d.overhead)
+    }
 
-        for t in self.0.iter() {
-            let wcet = t.wcet();
-            let util = wcet as f32 / t.inter_arrival as f32;
-            println!(
-                "id {}, start {}, end {}, inter_arrival {}, wcet {}, ratio {}",
-                t.id, t.trace.start, t.trace.end, t.inter_arrival, wcet, util
-            );
-            tot_util += util;
+    // The bcet of self, over all observed traces
+    pub fn bcet(&self) -> Time {
+        self.all_traces()
+            .map(|trace| trace.bcet())
+            .min()
+            .unwrap_or(0)
+    }
+
+    // The worst case critical section per resource, over all observed traces,
+    // with the path of trace ids leading to it
+    pub fn critical_section_paths(&self) -> HashMap<String, (Time, Vec<String>)> {
+        let mut cs = HashMap::new();
+        for trace in self.all_traces() {
+            update_critical_sections(trace, &mut vec![], &mut cs);
         }
-        tot_util
+        cs
     }
 
-    // The blocking of lower priority tasks to task t
-    pub fn blocking(&self, t: &Task, ip: &IdPrio) -> u32 {
-        let lower = self.lower(t);
+    // The worst case critical section length per resource, over all observed traces
+    pub fn critical_sections(&self) -> HashMap<String, Time> {
+        self.critical_section_paths()
+            .into_iter()
+            .map(|(r, (wcet, _))| (r, wcet))
+            .collect()
+    }
+}
 
-        let blocking = lower
+impl Tasks {
+    // The ceiling of each resource, as given by the resource table
+    pub fn pre_analysis(&self) -> IdPrio {
+        self.resource_table()
             .0
-            .iter()
-            .fold(0, |blocking, t1| blocking.max(t1.blocking(t, ip)));
-        println!("max blocking {}\n----\n", blocking);
-        blocking
+            .into_iter()
+            .map(|r| (r.id, r.ceiling))
+            .collect()
     }
 
-    // The interference of higher priority tasks to task t, starting from busy period
-    pub fn busy_period_rec(&self, t: &Task, wcet_and_block: u32, busy_period: u32) -> Option<u32> {
-        println!(
-            "interference to task {} during busy period {}",
-            t.id, busy_period
-        );
-        let interference = self.0.iter().fold(0, |interference, t1| {
-            let nr = 1 + busy_period / t1.inter_arrival;
-            let pre = nr * t1.wcet();
-            println!(
-                "interference by {},  {} = {} (times) * {} (wcet), inter_arrival {}",
-                t1.id,
-                pre,
-                nr,
-                t1.wcet(),
-                t1.inter_arrival
-            );
-            interference + pre
-        });
+    // Best case annotations are at most the wcet of their node, spawned tasks
+    // are in the task set, and the tasks of a dispatcher share its priority
+    pub fn validate(&self) -> Result<(), AnalysisError> {
+        self.validate_spans()?;
+        // priority 0 is idle, a set of idle tasks only has no priorities assigned
+        if !self.0.is_empty() && self.0.iter().all(|t| t.is_background()) {
+            return Err(AnalysisError::NoPriorities);
+        }
+        for t in &self.0 {
+            if t.checked_wcet().is_none() {
+                return Err(AnalysisError::Overflow(t.id.clone()));
+            }
+            if let Some(trace) = t.all_traces().find_map(|trace| trace.invalid_best()) {
+                return Err(AnalysisError::BestCase(t.id.clone(), trace.id.clone()));
+            }
+            if let Some(spawned) = t
+                .spawns
+                .iter()
+                .find(|s| !self.0.iter().any(|t| t.id == **s))
+            {
+                return Err(AnalysisError::UnknownSpawn(t.id.clone(), spawned.clone()));
+            }
+            if let Some(d) = &t.dispatch {
+                let same = |t1: &&Task| {
+                    t1.dispatch
+                        .as_ref()
+                        .is_some_and(|d1| d1.dispatcher == d.dispatcher)
+                };
+                if self.0.iter().filter(same).any(|t1| t1.prio != t.prio) {
+                    return Err(AnalysisError::Dispatcher(
+                        d.dispatcher.clone(),
+                        t.id.clone(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
 
-        let new_busy_period = wcet_and_block + interference;
-        println!(
-            "interference to task {}, new bp {}, deadline {}",
-            t.id, new_busy_period, t.deadline
-        );
-        if new_busy_period > t.deadline {
-            println!("task {} missed deadline", t.id);
-            None
-        } else if new_busy_period <= busy_period {
-            println!("task {} busy period {}", t.id, busy_period);
-            Some(new_busy_period)
-        } else {
-            println!("new busy period > busy period, solve recurrence again");
-            self.busy_period_rec(t, wcet_and_block, new_busy_period)
+    // Trace nodes end no earlier than they start
+    pub fn validate_spans(&self) -> Result<(), AnalysisError> {
+        for t in &self.0 {
+            if let Some(trace) = t.all_traces().find_map(|trace| trace.invalid_span()) {
+                return Err(AnalysisError::Span(t.id.clone(), trace.id.clone()));
+            }
         }
+        Ok(())
     }
 
-    // The exact interference of higher priority tasks to task t
-    pub fn busy_period(&self, t: &Task, wcet_and_block: u32) -> Option<u32> {
-        // interference by equal priority tasks
-        let higher_or_equal = self.higher_or_equal(t);
-        let equal_interference = higher_or_equal.busy_period_rec(t, 0, 0)?;
-        println!("busy_period: higher_or_equal {}", equal_interference);
+    // Whether task from spawns task to, directly or through other tasks
+    pub fn reaches(&self, from: &str, to: &str) -> bool {
+        let mut visited = vec![from];
+        let mut stack = vec![from];
+        while let Some(id) = stack.pop() {
+            let Some(t) = self.0.iter().find(|t| t.id == id) else {
+                continue;
+            };
+            for s in &t.spawns {
+                if s == to {
+                    return true;
+                }
+                if !visited.contains(&s.as_str()) {
+                    visited.push(s);
+                    stack.push(s);
+                }
+            }
+        }
+        false
+    }
 
-        // interference by higher priority tasks
-        let higher = self.higher(t);
-        let busy_period =
-            higher.busy_period_rec(t, wcet_and_block, wcet_and_block.max(equal_interference));
-        println!("busy_period: total {:?}", busy_period);
-        busy_period
+    // The release jitter of each task inherited from its spawners, given the
+    // worst and best case response times of the tasks and their current
+    // release jitter. A spawned task is released as its spawner completes,
+    // i.e., with the jitter of the spawner and its response jitter, None
+    // (unbounded) if the spawner is not schedulable. Spawns closing a cycle,
+    // e.g., a task spawning itself, re-release the task by its inter-arrival
+    // and carry no jitter.
+    pub fn inherited_jitter(
+        &self,
+        bounds: &[(Option<Time>, Time)],
+        release_jitter: &[Time],
+    ) -> Vec<Option<Time>> {
+        let mut jitter = vec![Some(0); self.0.len()];
+        for ((t, (response_time, best)), release_jitter) in
+            self.0.iter().zip(bounds).zip(release_jitter)
+        {
+            let inherited = response_time.map(|r| release_jitter + r.saturating_sub(*best));
+            for s in &t.spawns {
+                if self.reaches(s, &t.id) {
+                    continue;
+                }
+                if let Some(j) = self.0.iter().position(|t| t.id == *s) {
+                    jitter[j] = jitter[j].zip(inherited).map(|(a, b)| a.max(b));
+                }
+            }
+        }
+        jitter
     }
 
-    // response time analysis
-    pub fn response_time(&self) -> TasksResult {
-        let ip = self.pre_analysis();
-        println!("ip: {:?}", ip);
+    // total utilization, of all tasks but idle (at priority 0) running in the background
+    pub fn total_utilization(&self) -> f32 {
+        let mut tot_util = 0.0;
 
-        let tasks_results: Vec<TaskResult> = self
-            .0
-            .iter()
-            .map(|t| {
-                println!("analyzing task {}", t.id);
-                let blocking = self.blocking(t, &ip);
-                let response_time = self.busy_period(t, t.wcet() + blocking);
-
-                let wcet = t.wcet();
-                let interference = response_time.map(|rp| rp - (wcet + blocking));
-                let task_result = TaskResult {
-                    task: t.clone(),
-                    response_time: response_time,
-                    wcet,
-                    blocking,
-                    interference: interference,
-                };
-                println!("analyzing task {} done", t.id);
-                println!("{}", task_result);
-                task_result
-            })
-            .collect();
-        TasksResult(tasks_results)
+        for t in self.0.iter().filter(|t| !t.is_background()) {
+            let wcet = t.wcet() as f32 + t.overhead() as f32;
+            let util = wcet / t.inter_arrival as f32;
+            tot_util += util;
+        }
+        tot_util
+    }
+
+    // response time analysis, performed in the common time base of the task set
+    pub fn response_time(&self) -> Result<TasksResult, AnalysisError> {
+        let tasks = self.normalize()?;
+        tasks.validate()?;
+        let mut model = Model::new(&tasks)?;
+
+        // holistic analysis, iterated until the jitter inherited by spawned tasks is stable
+        for _ in 0..MAX_ITERATIONS {
+            let bounds = (0..tasks.0.len())
+                .map(|i| model.bounds(i))
+                .collect::<Result<Vec<_>, _>>()?;
+            let inherited = tasks.inherited_jitter(&bounds, &model.jitter);
+            let jitter: Vec<Time> = inherited.iter().map(|j| j.unwrap_or(0)).collect();
+            let unbounded: Vec<bool> = inherited.iter().map(|j| j.is_none()).collect();
+            if jitter == model.jitter && unbounded == model.unbounded {
+                let tasks_results = tasks
+                    .0
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, t)| model.response_time(i, t))
+                    .collect::<Result<_, _>>()?;
+                return Ok(TasksResult(tasks_results));
+            }
+            model.jitter = jitter;
+            model.unbounded = unbounded;
+        }
+        // not reached, as jitter is only inherited along acyclic spawns
+        let changing = (0..tasks.0.len())
+            .find(|i| model.jitter[*i] != 0)
+            .map_or_else(String::new, |i| tasks.0[i].id.clone());
+        Err(AnalysisError::IterationLimit(changing, MAX_ITERATIONS))
     }
 }
 
 // pub fn response_time
 
+impl Tasks {
+    // Response times by the classic recurrence R = C + B + sum ceil(R / A) * C,
+    // over higher and equal priority tasks, in the common time base of the task set.
+    // Unlike response_time, tasks only interfere by jobs released before R.
+    pub fn response_time_exact(&self) -> Result<Vec<Option<Time>>, AnalysisError> {
+        let tasks = self.normalize()?;
+        tasks.validate()?;
+        let model = Model::new(&tasks)?;
+        (0..tasks.0.len())
+            .map(|i| {
+                let t = &tasks.0[i];
+                let overflow = || AnalysisError::Overflow(t.id.clone());
+                let base = model.wcet_and_block(i)?;
+                let mut response_time = base;
+                for _ in 0..model.max_iterations {
+                    let interference = model
+                        .range(i, InterferenceKind::Higher)
+                        .chain(model.range(i, InterferenceKind::Equal))
+                        .try_fold(0 as Time, |interference, j| {
+                            let nr = response_time.div_ceil(model.inter_arrival[j].max(1));
+                            let pre = nr.checked_mul(model.wcet[j]).ok_or_else(overflow)?;
+                            interference.checked_add(pre).ok_or_else(overflow)
+                        })?;
+                    let next = base.checked_add(interference).ok_or_else(overflow)?;
+                    if next > t.deadline {
+                        return Ok(None);
+                    }
+                    if next == response_time {
+                        return Ok(Some(response_time));
+                    }
+                    response_time = next;
+                }
+                Err(AnalysisError::IterationLimit(
+                    t.id.clone(),
+                    model.max_iterations,
+                ))
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::common::*;
-    use std::{collections::HashMap, path::PathBuf};
+    use crate::model::Model;
+    use std::path::PathBuf;
+
+    #[test]
+    fn response_time_exact() {
+        let tasks = Tasks::load(&PathBuf::from("task_sets/task_set2.json")).unwrap();
+        // T2 is interfered by a single job of T3 at R = 50, where
+        // response_time counts 1 + 50 / 50 = 2 (giving 60)
+        assert_eq!(
+            tasks.response_time_exact().unwrap(),
+            vec![Some(70), Some(50), Some(20)]
+        );
+    }
+
+    #[test]
+    fn best_response_time_set2() {
+        let tasks = Tasks::load(&PathBuf::from("task_sets/task_set2.json")).unwrap();
+        let results = tasks.response_time().unwrap();
+        let best: Vec<Time> = results.0.iter().map(|r| r.best_response_time).collect();
+        // no blocking, and T3 cannot preempt within its inter-arrival of 50
+        assert_eq!(best, vec![20, 30, 10]);
+        let jitter: Vec<_> = results.0.iter().map(|r| r.jitter).collect();
+        assert_eq!(jitter, vec![Some(50), Some(30), Some(10)]);
+
+        // a best case annotation beyond the wcet is rejected
+        let mut tasks = tasks;
+        tasks.0[2].trace.best = Some(11);
+        assert_eq!(
+            tasks.response_time(),
+            Err(AnalysisError::BestCase("T3".to_string(), "T3".to_string()))
+        );
+    }
+
+    #[test]
+    fn dispatch_set2() {
+        let mut tasks = Tasks::load(&PathBuf::from("task_sets/task_set2.json")).unwrap();
+        // T3 a software task, spawned every 15
+        tasks.0[2].inter_arrival = 15;
+        tasks.0[2].dispatch = Some(Dispatch {
+            dispatcher: "EXTI1".to_string(),
+            capacity: 1,
+            overhead: 2,
+        });
+        let results = tasks.response_time().unwrap();
+        let t3 = &results.0[2];
+        // wcet 10 + overhead 2, blocking 10
+        assert_eq!(t3.wcet, 12);
+        assert_eq!(t3.response_time, Some(22));
+        // a second message may be posted before the first completes
+        assert_eq!(t3.required_capacity, Some(2));
+        assert_eq!(results.0[1].required_capacity, None);
+
+        // the tasks of a dispatcher share its priority
+        tasks.0[1].dispatch = tasks.0[2].dispatch.clone();
+        assert_eq!(
+            tasks.response_time(),
+            Err(AnalysisError::Dispatcher(
+                "EXTI1".to_string(),
+                "T2".to_string()
+            ))
+        );
+    }
 
     #[test]
     fn tot_util_task_set1() {
@@ -228,60 +437,332 @@ mod test {
     #[test]
     fn response_time_set1() {
         let tasks = Tasks::load(&PathBuf::from("task_sets/task_set1.json")).unwrap();
-        let response_time = tasks.response_time();
+        let response_time = tasks.response_time().unwrap();
         println!("{}", response_time);
     }
 
     #[test]
     fn response_time_set2() {
         let tasks = Tasks::load(&PathBuf::from("task_sets/task_set2.json")).unwrap();
-        let response_time = tasks.response_time();
+        let response_time = tasks.response_time().unwrap();
         println!("{}", response_time);
     }
 
     #[test]
     fn response_time_set3() {
         let tasks = Tasks::load(&PathBuf::from("task_sets/task_set3.json")).unwrap();
-        let response_time = tasks.response_time();
+        let response_time = tasks.response_time().unwrap();
         println!("{}", response_time);
     }
 
+    #[test]
+    fn blocker_set1() {
+        let tasks = crate::task_sets::task_set1();
+        let results = tasks.response_time().unwrap();
+        assert_eq!(results.0[0].blocker, None);
+        assert_eq!(
+            results.0[2].blocker,
+            Some(Blocker {
+                task: "T2".to_string(),
+                resource: "R2".to_string(),
+                path: vec!["T2".to_string(), "R1".to_string(), "R2".to_string()],
+                length: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn interferers_set2() {
+        let tasks = Tasks::load(&PathBuf::from("task_sets/task_set2.json")).unwrap();
+        let results = tasks.response_time().unwrap();
+        let t1 = &results.0[0];
+        assert_eq!(
+            t1.interferers,
+            vec![
+                Interferer {
+                    task: "T3".to_string(),
+                    kind: InterferenceKind::Higher,
+                    preemptions: 2,
+                    cost: 20,
+                },
+                Interferer {
+                    task: "T2".to_string(),
+                    kind: InterferenceKind::Higher,
+                    preemptions: 1,
+                    cost: 30,
+                },
+            ]
+        );
+        let cost: Time = t1.interferers.iter().map(|i| i.cost).sum();
+        assert_eq!(t1.interference, Some(cost));
+    }
+
+    #[test]
+    fn interferers_equal_set2() {
+        let mut tasks = Tasks::load(&PathBuf::from("task_sets/task_set2.json")).unwrap();
+        tasks.0[0].prio = tasks.0[1].prio;
+        let results = tasks.response_time().unwrap();
+        for r in &results.0 {
+            let cost: Time = r.interferers.iter().map(|i| i.cost).sum();
+            assert_eq!(r.interference, Some(cost));
+        }
+        let kinds: Vec<_> = results.0[0].interferers.iter().map(|i| i.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![InterferenceKind::Equal, InterferenceKind::Higher]
+        );
+    }
+
+    #[test]
+    fn busy_period_overflow() {
+        let t1 = Task {
+            id: "T1".to_string(),
+            prio: 1,
+            deadline: Time::MAX,
+            inter_arrival: Time::MAX,
+            trace: Trace {
+                id: "T1".to_string(),
+                start: 0,
+                end: 10,
+                kind: TraceKind::Section,
+                best: None,
+                inner: vec![],
+            },
+            traces: vec![],
+            time_base: TimeBase::default(),
+            spawns: vec![],
+            periodic: false,
+            dispatch: None,
+        };
+        let t2 = Task {
+            id: "T2".to_string(),
+            prio: 2,
+            deadline: 1,
+            inter_arrival: 1,
+            trace: Trace {
+                id: "T2".to_string(),
+                start: 0,
+                end: Time::MAX / 2,
+                kind: TraceKind::Section,
+                best: None,
+                inner: vec![],
+            },
+            traces: vec![],
+            time_base: TimeBase::default(),
+            spawns: vec![],
+            periodic: true,
+            dispatch: None,
+        };
+        let mut tasks = Tasks(vec![t1, t2]);
+        assert_eq!(
+            tasks.response_time(),
+            Err(AnalysisError::Overflow("T1".to_string()))
+        );
+
+        // a zero inter-arrival is rejected rather than divided by
+        tasks.0[1].inter_arrival = 0;
+        assert_eq!(
+            tasks.response_time(),
+            Err(AnalysisError::InterArrival("T2".to_string()))
+        );
+
+        // as are a loop exceeding the time representation, and a trace
+        // ending before it starts
+        tasks.0[1].trace.kind = TraceKind::Loop(3);
+        assert_eq!(
+            tasks.response_time(),
+            Err(AnalysisError::Overflow("T2".to_string()))
+        );
+        tasks.0[0].trace.start = 20;
+        assert_eq!(
+            tasks.response_time(),
+            Err(AnalysisError::Span("T1".to_string(), "T1".to_string()))
+        );
+    }
+
     #[test]
     fn test_blocking() {
         let trace = Trace {
             id: "R1".to_string(),
             start: 10,
             end: 20,
+            kind: TraceKind::Section,
+            best: None,
             inner: vec![
                 Trace {
                     id: "R2".to_string(),
                     start: 12,
                     end: 14,
+                    kind: TraceKind::Section,
+                    best: None,
                     inner: vec![],
                 },
                 Trace {
                     id: "R2".to_string(),
                     start: 14,
                     end: 18,
+                    kind: TraceKind::Section,
+                    best: None,
                     inner: vec![],
                 },
             ],
         };
 
-        let t0 = Task::default();
+        let task = |id: &str, prio, inner| Task {
+            id: id.to_string(),
+            prio,
+            trace: Trace {
+                id: id.to_string(),
+                start: 0,
+                end: 30,
+                kind: TraceKind::Section,
+                best: None,
+                inner,
+            },
+            ..Task::default()
+        };
+        let section = |id: &str| Trace {
+            id: id.to_string(),
+            start: 0,
+            end: 1,
+            kind: TraceKind::Section,
+            best: None,
+            inner: vec![],
+        };
+        // R1 has ceiling 2 and R2 ceiling 3
+        let tasks = Tasks(vec![
+            task("L", 1, vec![trace]),
+            task("M", 2, vec![section("R1")]),
+            task("H", 3, vec![section("R2")]),
+        ]);
+        let model = Model::new(&tasks).unwrap();
+
+        assert_eq!(model.blocking(0), 0);
+        // the outer section R1, including its nested sections
+        assert_eq!(model.blocking(1), 10);
+        // the longest nested section R2
+        let b = model.blocker(2).unwrap();
+        assert_eq!((b.task.as_str(), b.resource.as_str()), ("L", "R2"));
+        assert_eq!(b.length, 4);
+        assert_eq!(b.path, ["L", "R1", "R2"]);
+    }
+
+    #[test]
+    fn test_blocking_traces() {
+        let cs = |id: &str, start, end| Trace {
+            id: id.to_string(),
+            start,
+            end,
+            kind: TraceKind::Section,
+            best: None,
+            inner: vec![],
+        };
         let t1 = Task {
+            id: "T1".to_string(),
             prio: 1,
+            deadline: 100,
+            inter_arrival: 100,
+            trace: Trace {
+                id: "T1".to_string(),
+                start: 0,
+                end: 20,
+                kind: TraceKind::Section,
+                best: None,
+                inner: vec![cs("R1", 2, 10), cs("R2", 12, 14)],
+            },
+            traces: vec![Trace {
+                id: "T1".to_string(),
+                start: 0,
+                end: 30,
+                kind: TraceKind::Section,
+                best: None,
+                inner: vec![cs("R1", 2, 4), cs("R2", 10, 16)],
+            }],
+            time_base: TimeBase::default(),
+            spawns: vec![],
+            periodic: false,
+            dispatch: None,
+        };
+        assert_eq!(t1.wcet(), 30);
+
+        let sections = t1.critical_sections();
+        assert_eq!(sections.get("R1"), Some(&8));
+        assert_eq!(sections.get("R2"), Some(&6));
+
+        // R2 is shared with t2, R1 is local to t1
+        let t2 = Task {
+            id: "T2".to_string(),
+            prio: 2,
+            trace: Trace {
+                id: "T2".to_string(),
+                start: 0,
+                end: 5,
+                kind: TraceKind::Section,
+                best: None,
+                inner: vec![cs("R2", 1, 2)],
+            },
             ..Task::default()
         };
+        let model = Model::new(&Tasks(vec![t1, t2])).unwrap();
+        assert_eq!(model.blocking(1), 6);
+    }
 
-        let mut ip = HashMap::new();
-        ip.insert("R1".to_string(), 1);
-        ip.insert("R2".to_string(), 2);
-
-        let cs = trace.blocking(&t0, &ip);
-        println!("cs {:?}", cs);
+    #[test]
+    fn test_wcet_alt_loop() {
+        let node = |id: &str, start, end, kind, inner| Trace {
+            id: id.to_string(),
+            start,
+            end,
+            kind,
+            best: None,
+            inner,
+        };
+        // T1 [0..40] with an alternative at [5..25], either R1 [5..15] or an
+        // error path [5..25] holding R2 [10..12] within a loop of at most 4 rounds
+        let trace = node(
+            "T1",
+            0,
+            40,
+            TraceKind::Section,
+            vec![node(
+                "A",
+                5,
+                25,
+                TraceKind::Alt,
+                vec![
+                    node("R1", 5, 15, TraceKind::Section, vec![]),
+                    node(
+                        "E",
+                        5,
+                        25,
+                        TraceKind::Path,
+                        vec![node(
+                            "L",
+                            8,
+                            14,
+                            TraceKind::Loop(4),
+                            vec![node("R2", 10, 12, TraceKind::Section, vec![])],
+                        )],
+                    ),
+                ],
+            )],
+        );
+        // error path: 14 (own) + 4 * 6 (loop) = 38, T1: 20 (own) + 38 = 58
+        assert_eq!(trace.wcet(), 58);
 
-        let cs = trace.blocking(&t1, &ip);
-        println!("cs {:?}", cs);
+        let t1 = Task {
+            id: "T1".to_string(),
+            prio: 1,
+            trace,
+            ..Task::default()
+        };
+        let cs = t1.critical_sections();
+        assert_eq!(cs.get("R1"), Some(&10));
+        assert_eq!(cs.get("R2"), Some(&2));
+        assert_eq!(cs.get("A"), None);
+
+        let tasks = Tasks(vec![t1]);
+        let ip = tasks.pre_analysis();
+        assert!(!ip.contains_key("L"));
     }
 }
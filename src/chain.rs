@@ -0,0 +1,185 @@
+// End-to-end latency of task chains
+//
+// A chain is a sequence of tasks, each spawning the next, e.g., an interrupt
+// handler spawning processing spawning actuation. A spawned task is released
+// at the latest as its spawner completes, so the latency from the release of
+// the first task to the completion of the last is bounded by the sum of their
+// response times, each accounting for the jitter inherited along the chain.
+
+use crate::common::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// A chain of task ids, given as `T1,T2,T3`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chain(pub Vec<String>);
+
+impl FromStr for Chain {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ids: Vec<String> = s
+            .split(',')
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .collect();
+        if ids.is_empty() {
+            return Err("empty chain".to_string());
+        }
+        Ok(Chain(ids))
+    }
+}
+
+impl fmt::Display for Chain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join(" > "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainError {
+    // task
+    UnknownTask(String),
+    // spawner, task not spawned by it
+    NotSpawned(String, String),
+    // the latency of the chain cannot be computed, e.g., on overflow
+    Analysis(AnalysisError),
+}
+
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainError::UnknownTask(t) => write!(f, "task {} not in task set", t),
+            ChainError::NotSpawned(s, t) => write!(f, "task {} does not spawn {}", s, t),
+            ChainError::Analysis(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ChainError {}
+
+// The worst and best case latency of a chain, None if some task of the chain
+// may miss its deadline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Latency {
+    pub chain: Chain,
+    pub latency: Option<Time>,
+    pub best: Time,
+    // per task, its response time
+    pub hops: Vec<(String, Option<Time>)>,
+}
+
+impl fmt::Display for Latency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "chain         {}", self.chain)?;
+        writeln!(f, "latency       {:?}", self.latency)?;
+        writeln!(f, "best          {}", self.best)?;
+        for (t, r) in &self.hops {
+            writeln!(f, "hop           {} {:?}", t, r)?;
+        }
+        Ok(())
+    }
+}
+
+impl Chain {
+    // The latency of self, given the (holistic) analysis results of the task set
+    pub fn latency(&self, results: &TasksResult) -> Result<Latency, ChainError> {
+        let overflow = || ChainError::Analysis(AnalysisError::Overflow(self.to_string()));
+        let mut hops = vec![];
+        let mut best: Time = 0;
+        for (k, id) in self.0.iter().enumerate() {
+            let r = results
+                .0
+                .iter()
+                .find(|r| r.task.id == *id)
+                .ok_or_else(|| ChainError::UnknownTask(id.clone()))?;
+            if let Some(next) = self.0.get(k + 1) {
+                if !r.task.spawns.contains(next) {
+                    return Err(ChainError::NotSpawned(id.clone(), next.clone()));
+                }
+            }
+            best = best
+                .checked_add(r.best_response_time)
+                .ok_or_else(overflow)?;
+            hops.push((id.clone(), r.response_time));
+        }
+        // None if some task may miss its deadline
+        let latency = hops
+            .iter()
+            .map(|(_, r)| *r)
+            .collect::<Option<Vec<Time>>>()
+            .map(|rs| {
+                rs.into_iter()
+                    .try_fold(0, Time::checked_add)
+                    .ok_or_else(overflow)
+            })
+            .transpose()?;
+        Ok(Latency {
+            chain: self.clone(),
+            latency,
+            best,
+            hops,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn latency_set2() {
+        let mut tasks = Tasks::load(&PathBuf::from("task_sets/task_set2.json")).unwrap();
+        let before = tasks.response_time().unwrap();
+
+        // T2 spawns T3 (of highest priority)
+        tasks.0[1].spawns = vec!["T3".to_string()];
+        let results = tasks.response_time().unwrap();
+        // T3 inherits the response jitter of T2 (60 - 30), and preempts T1
+        // once more (1 + (70 + 30) / 50 times)
+        assert_eq!(results.0[2].release_jitter, 30);
+        assert_eq!(results.0[0].response_time, Some(80));
+
+        let chain: Chain = "T2,T3".parse().unwrap();
+        let latency = chain.latency(&results).unwrap();
+        assert_eq!(latency.latency, Some(60 + 20));
+        assert_eq!(latency.best, 30 + 10);
+        assert_eq!(
+            "T3,T2".parse::<Chain>().unwrap().latency(&results),
+            Err(ChainError::NotSpawned("T3".to_string(), "T2".to_string()))
+        );
+
+        // a spawner missing its deadline releases T3 with unbounded jitter,
+        // so neither T3 nor the tasks it interferes with have a bound
+        tasks.0[1].deadline = 50;
+        let results = tasks.response_time().unwrap();
+        assert!(results.0.iter().all(|r| r.response_time.is_none()));
+        assert_eq!(chain.latency(&results).unwrap().latency, None);
+        tasks.0[1].deadline = 100;
+
+        // a task spawning itself is periodic, and inherits no jitter
+        tasks.0[1].spawns = vec![];
+        tasks.0[2].spawns = vec!["T3".to_string()];
+        let response_time =
+            |r: &TasksResult| -> Vec<_> { r.0.iter().map(|r| r.response_time).collect() };
+        let results = tasks.response_time().unwrap();
+        assert!(results.0.iter().all(|r| r.release_jitter == 0));
+        assert_eq!(response_time(&results), response_time(&before));
+
+        // latencies not fitting the time representation are reported
+        let overflow = Err(ChainError::Analysis(AnalysisError::Overflow(
+            "T2 > T3".to_string(),
+        )));
+        tasks.0[1].spawns = vec!["T3".to_string()];
+        tasks.0[2].spawns = vec![];
+        let mut results = tasks.response_time().unwrap();
+        results.0[2].response_time = Some(Time::MAX);
+        assert_eq!(chain.latency(&results), overflow);
+        results.0[2].response_time = None;
+        assert_eq!(chain.latency(&results).unwrap().latency, None);
+        results.0[2].best_response_time = Time::MAX;
+        assert_eq!(chain.latency(&results), overflow);
+    }
+}
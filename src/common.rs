@@ -4,13 +4,77 @@ use std::fmt;
 
 // common data structures
 
+// The representation of time (and durations) throughout the analysis
+pub type Time = u64;
+
+// The unit in which timing values (deadline, inter_arrival, trace timestamps) are given
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeUnit {
+    #[default]
+    Cycles,
+    Ns,
+    Us,
+    Ms,
+    S,
+}
+
+// The time unit of a task, cycles are converted to time by the clock frequency
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TimeBase {
+    pub unit: TimeUnit,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock_hz: Option<u64>,
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Task {
     pub id: String,
     pub prio: u8,
-    pub deadline: u32,
-    pub inter_arrival: u32,
+    pub deadline: Time,
+    pub inter_arrival: Time,
     pub trace: Trace,
+    // Further observed executions of the task, besides trace
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub traces: Vec<Trace>,
+    #[serde(default, skip_serializing_if = "TimeBase::is_default")]
+    pub time_base: TimeBase,
+    // Tasks spawned by the task, released as it completes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spawns: Vec<String>,
+    // Released strictly periodically, rather than at least inter_arrival apart
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub periodic: bool,
+    // The dispatcher of a software task, None for a hardware task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dispatch: Option<Dispatch>,
+}
+
+fn default_capacity() -> usize {
+    1
+}
+
+// A software task (RTIC v1) is queued on spawn and run by a dispatcher
+// interrupt at its priority, shared by the software tasks of that priority
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Dispatch {
+    pub dispatcher: String,
+    // declared capacity of the message queue of the task
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,
+    // execution time of the dispatcher per message
+    #[serde(default)]
+    pub overhead: Time,
+}
+
+impl fmt::Display for Dispatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, capacity {}, overhead {}",
+            self.dispatcher, self.capacity, self.overhead
+        )
+    }
 }
 
 impl fmt::Display for Task {
@@ -19,21 +83,64 @@ impl fmt::Display for Task {
         writeln!(f, "prio          {}", self.prio)?;
         writeln!(f, "deadline      {}", self.deadline)?;
         writeln!(f, "inter_arrival {}", self.inter_arrival)?;
-        writeln!(f, "trace:\n{}", self.trace)
+        writeln!(f, "time_base     {}", self.time_base)?;
+        if !self.spawns.is_empty() {
+            writeln!(f, "spawns        {}", self.spawns.join(", "))?;
+        }
+        if let Some(dispatch) = &self.dispatch {
+            writeln!(f, "dispatch      {}", dispatch)?;
+        }
+        writeln!(f, "trace:\n{}", self.trace)?;
+        for trace in &self.traces {
+            writeln!(f, "trace:\n{}", trace)?;
+        }
+        Ok(())
     }
 }
 
+// The kind of a trace node
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceKind {
+    // The task itself or a critical section on the resource id
+    #[default]
+    Section,
+    // A sequence of inner nodes, not holding any resource
+    Path,
+    // Alternative control flow paths, one of the inner nodes is taken
+    Alt,
+    // A loop executing the inner nodes at most bound times
+    Loop(u32),
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Trace {
     pub id: String,
-    pub start: u32,
-    pub end: u32,
+    pub start: Time,
+    pub end: Time,
+    #[serde(default, skip_serializing_if = "TraceKind::is_section")]
+    pub kind: TraceKind,
+    // the best case duration of the node (including inner nodes), at most its wcet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best: Option<Time>,
     pub inner: Vec<Trace>,
 }
 
+impl TraceKind {
+    pub fn is_section(&self) -> bool {
+        *self == TraceKind::Section
+    }
+}
+
 impl fmt::Display for Trace {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "{} [{}...{}]", self.id, self.start, self.end)?;
+        match self.kind {
+            TraceKind::Section => write!(f, "{}", self.id)?,
+            TraceKind::Path => write!(f, "{} path", self.id)?,
+            TraceKind::Alt => write!(f, "{} alt", self.id)?,
+            TraceKind::Loop(bound) => write!(f, "{} loop({})", self.id, bound)?,
+        }
+        writeln!(f, " [{}...{}]", self.start, self.end)?;
 
         for i in &self.inner {
             write!(f, "{}", indented(i))?
@@ -65,7 +172,11 @@ impl Tasks {
         let mut contents = String::new();
         let _size = file.read_to_string(&mut contents)?;
         // Convert the JSON string to Tasks.
-        let deserialized: Tasks = serde_json::from_str(&contents).unwrap();
+        let deserialized: Tasks = serde_json::from_str(&contents)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        deserialized
+            .validate_spans()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
         Ok(deserialized)
     }
 
@@ -77,22 +188,135 @@ impl Tasks {
     }
 }
 
+// The critical section of a lower priority task causing the blocking of a task
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Blocker {
+    pub task: String,
+    pub resource: String,
+    // trace ids from the task to the critical section
+    pub path: Vec<String>,
+    pub length: Time,
+}
+
+impl fmt::Display for Blocker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} on {} ({}) length {}",
+            self.task,
+            self.resource,
+            self.path.join(" > "),
+            self.length
+        )
+    }
+}
+
+// Whether an interfering task has equal or higher priority
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InterferenceKind {
+    Equal,
+    Higher,
+}
+
+// The interference by a single task, at the fix-point of the busy period
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Interferer {
+    pub task: String,
+    pub kind: InterferenceKind,
+    pub preemptions: Time,
+    pub cost: Time,
+}
+
+impl fmt::Display for Interferer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            InterferenceKind::Equal => "equal",
+            InterferenceKind::Higher => "higher",
+        };
+        write!(
+            f,
+            "{} ({}) {} times, cost {}",
+            self.task, kind, self.preemptions, self.cost
+        )
+    }
+}
+
+// A step of the busy period recurrence of a job, from busy_period to next
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Iteration {
+    #[serde(default)]
+    pub job: usize,
+    pub busy_period: Time,
+    pub next: Time,
+}
+
+impl fmt::Display for Iteration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "job {}: {} -> {}", self.job, self.busy_period, self.next)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct TaskResult {
     pub task: Task,
-    pub response_time: Option<u32>,
-    pub wcet: u32,
-    pub blocking: u32,
-    pub interference: Option<u32>,
+    pub response_time: Option<Time>,
+    pub wcet: Time,
+    pub blocking: Time,
+    pub blocker: Option<Blocker>,
+    pub interference: Option<Time>,
+    #[serde(default)]
+    pub interferers: Vec<Interferer>,
+    // the release jitter inherited from spawning tasks
+    #[serde(default)]
+    pub release_jitter: Time,
+    // the queue capacity needed by a software task, None for a hardware task
+    // or if not schedulable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_capacity: Option<usize>,
+    #[serde(default)]
+    pub bcet: Time,
+    // the best case response time, without blocking and with minimal interference
+    #[serde(default)]
+    pub best_response_time: Time,
+    // the response time jitter, response_time - best_response_time
+    #[serde(default)]
+    pub jitter: Option<Time>,
+    // the iterations of the busy period recurrences, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub iterations: Vec<Iteration>,
 }
 
 impl fmt::Display for TaskResult {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "task          {}", self.task.id)?;
         writeln!(f, "response_time {:?}", self.response_time)?;
+        writeln!(f, "best_response {}", self.best_response_time)?;
+        writeln!(f, "jitter        {:?}", self.jitter)?;
+        if self.release_jitter != 0 {
+            writeln!(f, "release_jitter {}", self.release_jitter)?;
+        }
+        if let Some(dispatch) = &self.task.dispatch {
+            writeln!(
+                f,
+                "capacity      {:?} required, {} declared",
+                self.required_capacity, dispatch.capacity
+            )?;
+        }
         writeln!(f, "wcet          {}", self.wcet)?;
+        writeln!(f, "bcet          {}", self.bcet)?;
         writeln!(f, "blocking      {}", self.blocking)?;
+        if let Some(blocker) = &self.blocker {
+            writeln!(f, "blocked by    {}", blocker)?;
+        }
         writeln!(f, "interference  {:?}", self.interference)?;
+        for i in &self.interferers {
+            writeln!(f, "interferer    {}", i)?;
+        }
+        for i in &self.iterations {
+            writeln!(f, "iteration     {}", i)?;
+        }
+        writeln!(f, "time_base     {}", self.task.time_base)?;
         writeln!(f)
     }
 }
@@ -110,6 +334,106 @@ impl fmt::Display for TasksResult {
     }
 }
 
+// Errors reported by the analysis
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnalysisError {
+    // A task given in cycles lacks the clock frequency needed for conversion
+    MissingClock(String),
+    // Tasks given in cycles disagree on the clock frequency
+    ClockMismatch(u64, u64),
+    // A value cannot be represented exactly in the target time base
+    LossyConversion(String, Time),
+    // A value does not fit the time representation
+    Overflow(String),
+    // The busy period of a task did not converge within a number of iterations
+    IterationLimit(String, usize),
+    // The best case of a trace node (task, node id) exceeds its wcet
+    BestCase(String, String),
+    // A task (spawner, spawned) spawns a task not in the task set
+    UnknownSpawn(String, String),
+    // Tasks of a dispatcher (dispatcher, task) differ in priority
+    Dispatcher(String, String),
+    // A task has an inter-arrival of 0
+    InterArrival(String),
+    // A trace node (task, node id) ends before it starts
+    Span(String, String),
+    // No task has a priority assigned, i.e., above 0 (idle)
+    NoPriorities,
+}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalysisError::MissingClock(id) => {
+                write!(
+                    f,
+                    "task {} is given in cycles without a clock frequency",
+                    id
+                )
+            }
+            AnalysisError::ClockMismatch(a, b) => {
+                write!(f, "conflicting clock frequencies {} Hz and {} Hz", a, b)
+            }
+            AnalysisError::LossyConversion(id, v) => {
+                write!(f, "value {} of {} cannot be converted without loss", v, id)
+            }
+            AnalysisError::Overflow(id) => write!(f, "time overflow in {}", id),
+            AnalysisError::IterationLimit(id, n) => write!(
+                f,
+                "busy period of task {} did not converge within {} iterations",
+                id, n
+            ),
+            AnalysisError::BestCase(id, node) => write!(
+                f,
+                "best case of {} in task {} exceeds its worst case",
+                node, id
+            ),
+            AnalysisError::UnknownSpawn(id, spawned) => {
+                write!(f, "task {} spawns unknown task {}", id, spawned)
+            }
+            AnalysisError::Dispatcher(d, id) => write!(
+                f,
+                "task {} differs in priority from other tasks of dispatcher {}",
+                id, d
+            ),
+            AnalysisError::InterArrival(id) => write!(f, "task {} has no inter-arrival time", id),
+            AnalysisError::Span(id, node) => {
+                write!(f, "{} in task {} ends before it starts", node, id)
+            }
+            AnalysisError::NoPriorities => write!(
+                f,
+                "no task has a priority above 0 (idle), assign priorities (e.g., by merge)"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AnalysisError {}
+
+// Errors reported when importing task sets from other formats
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        ParseError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -0,0 +1,266 @@
+// Differences between two task sets, and their analysis results
+//
+// Values are compared in the common time base of both task sets.
+
+use crate::common::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+fn change<T: PartialEq>(old: T, new: T) -> Option<Change<T>> {
+    if old == new {
+        None
+    } else {
+        Some(Change { old, new })
+    }
+}
+
+// The changes of a task present in both sets, None for unchanged values
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskDiff {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prio: Option<Change<u8>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<Change<Time>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inter_arrival: Option<Change<Time>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wcet: Option<Change<Time>>,
+    // longest critical section per resource, None if not locked
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub critical_sections: BTreeMap<String, Change<Option<Time>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocking: Option<Change<Time>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interference: Option<Change<Option<Time>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_time: Option<Change<Option<Time>>>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<TaskDiff>,
+}
+
+impl TaskDiff {
+    fn new(old: &Task, new: &Task) -> TaskDiff {
+        let old_cs = old.critical_sections();
+        let new_cs = new.critical_sections();
+        let resources: BTreeSet<&String> = old_cs.keys().chain(new_cs.keys()).collect();
+        TaskDiff {
+            id: new.id.clone(),
+            prio: change(old.prio, new.prio),
+            deadline: change(old.deadline, new.deadline),
+            inter_arrival: change(old.inter_arrival, new.inter_arrival),
+            wcet: change(old.wcet(), new.wcet()),
+            critical_sections: resources
+                .into_iter()
+                .filter_map(|r| {
+                    change(old_cs.get(r).copied(), new_cs.get(r).copied()).map(|c| (r.clone(), c))
+                })
+                .collect(),
+            ..TaskDiff::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self
+            == TaskDiff {
+                id: self.id.clone(),
+                ..TaskDiff::default()
+            }
+    }
+}
+
+// helper functions
+
+// Both task sets in their common time base
+fn normalize(old: &Tasks, new: &Tasks) -> Result<(Tasks, Tasks), AnalysisError> {
+    let mut old = Tasks(old.0.iter().chain(&new.0).cloned().collect())
+        .normalize()?
+        .0;
+    let new = old.split_off(old.len() - new.0.len());
+    Ok((Tasks(old), Tasks(new)))
+}
+
+fn opt(t: &Option<Time>) -> String {
+    match t {
+        Some(t) => t.to_string(),
+        None => "-".to_string(),
+    }
+}
+
+fn line<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    name: &str,
+    c: Option<&Change<T>>,
+) -> fmt::Result {
+    match c {
+        Some(c) => writeln!(f, "  {:<22}{} -> {}", name, c.old, c.new),
+        None => Ok(()),
+    }
+}
+
+fn line_opt(
+    f: &mut fmt::Formatter<'_>,
+    name: &str,
+    c: Option<&Change<Option<Time>>>,
+) -> fmt::Result {
+    match c {
+        Some(c) => writeln!(f, "  {:<22}{} -> {}", name, opt(&c.old), opt(&c.new)),
+        None => Ok(()),
+    }
+}
+
+impl fmt::Display for TaskDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "task {}", self.id)?;
+        line(f, "prio", self.prio.as_ref())?;
+        line(f, "deadline", self.deadline.as_ref())?;
+        line(f, "inter_arrival", self.inter_arrival.as_ref())?;
+        line(f, "wcet", self.wcet.as_ref())?;
+        for (r, c) in &self.critical_sections {
+            line_opt(f, &format!("critical section {}", r), Some(c))?;
+        }
+        line(f, "blocking", self.blocking.as_ref())?;
+        line_opt(f, "interference", self.interference.as_ref())?;
+        line_opt(f, "response_time", self.response_time.as_ref())
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for t in &self.added {
+            writeln!(f, "added task {}", t)?;
+        }
+        for t in &self.removed {
+            writeln!(f, "removed task {}", t)?;
+        }
+        for t in &self.changed {
+            write!(f, "{}", t)?;
+        }
+        Ok(())
+    }
+}
+
+impl Diff {
+    // Differences of the task sets, matching tasks by id
+    pub fn tasks(old: &Tasks, new: &Tasks) -> Result<Diff, AnalysisError> {
+        let (old, new) = normalize(old, new)?;
+        Ok(Diff::new(old.0.iter(), new.0.iter(), |_, _| ()))
+    }
+
+    // Differences of the task sets analyzed, including blocking, interference
+    // and response time
+    pub fn results(old: &TasksResult, new: &TasksResult) -> Result<Diff, AnalysisError> {
+        let to = Tasks(old.0.iter().chain(&new.0).map(|r| r.task.clone()).collect())
+            .common_time_base()?;
+        let (old, new) = (old.convert(&to)?, new.convert(&to)?);
+        Ok(Diff::new(
+            old.0.iter().map(|r| &r.task),
+            new.0.iter().map(|r| &r.task),
+            |d, id| {
+                let o = old.0.iter().find(|r| r.task.id == id).unwrap();
+                let n = new.0.iter().find(|r| r.task.id == id).unwrap();
+                d.blocking = change(o.blocking, n.blocking);
+                d.interference = change(o.interference, n.interference);
+                d.response_time = change(o.response_time, n.response_time);
+            },
+        ))
+    }
+
+    fn new<'a>(
+        old: impl Iterator<Item = &'a Task> + Clone,
+        new: impl Iterator<Item = &'a Task> + Clone,
+        mut results: impl FnMut(&mut TaskDiff, &str),
+    ) -> Diff {
+        let mut diff = Diff::default();
+        for t in old.clone() {
+            if !new.clone().any(|n| n.id == t.id) {
+                diff.removed.push(t.id.clone());
+            }
+        }
+        for n in new {
+            match old.clone().find(|t| t.id == n.id) {
+                None => diff.added.push(n.id.clone()),
+                Some(t) => {
+                    let mut d = TaskDiff::new(t, n);
+                    results(&mut d, &n.id);
+                    if !d.is_empty() {
+                        diff.changed.push(d);
+                    }
+                }
+            }
+        }
+        diff
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn diff_set1() {
+        let old = crate::task_sets::task_set1();
+        let mut new = Tasks(old.0.clone());
+        new.0.remove(0);
+        // T2 holds R1 for 5 more
+        new.0[0].trace.inner[0].end += 5;
+        new.0[0].trace.end += 5;
+
+        let diff = Diff::tasks(&old, &new).unwrap();
+        assert_eq!(diff.removed, vec!["T1"]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(
+            diff.changed[0].critical_sections["R1"],
+            Change {
+                old: Some(10),
+                new: Some(15)
+            }
+        );
+
+        let diff =
+            Diff::results(&old.response_time().unwrap(), &new.response_time().unwrap()).unwrap();
+        assert_eq!(
+            diff.to_string(),
+            "removed task T1\n\
+             task T2\n\
+             \x20 wcet                  30 -> 35\n\
+             \x20 critical section R1   10 -> 15\n\
+             \x20 response_time         90 -> 95\n"
+        );
+
+        // the same set in us and in cycles at 2 MHz does not differ
+        let mut us = Tasks(old.0.clone());
+        for t in us.0.iter_mut() {
+            t.time_base.unit = TimeUnit::Us;
+        }
+        let mhz = TimeBase {
+            unit: TimeUnit::Cycles,
+            clock_hz: Some(2_000_000),
+        };
+        let mut cycles = Tasks(us.0.iter().map(|t| t.normalize(&mhz).unwrap()).collect());
+        let diff = Diff::results(
+            &us.response_time().unwrap(),
+            &cycles.response_time().unwrap(),
+        );
+        assert_eq!(diff, Ok(Diff::default()));
+        for t in cycles.0.iter_mut() {
+            t.time_base.clock_hz = None;
+        }
+        assert_eq!(
+            Diff::tasks(&us, &cycles),
+            Err(AnalysisError::MissingClock("T1".to_string()))
+        );
+    }
+}
@@ -0,0 +1,456 @@
+// draw.io diagram export and import
+//
+// Each task is drawn as a lane, holding a box per trace on a horizontal time
+// axis with critical sections as nested boxes. Task and node properties are
+// stored as draw.io data (`<object>` attributes), while times are taken from
+// the geometry, so traces can be edited visually and imported back.
+
+use crate::common::*;
+use crate::escape::{escape, unescape};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Width of the lane header holding the task name
+const HEADER: f64 = 40.0;
+// Width of the time axis of the longest trace
+const WIDTH: f64 = 800.0;
+const TRACE_HEIGHT: f64 = 60.0;
+
+// helper functions
+fn kind_attrs(kind: &TraceKind) -> String {
+    match kind {
+        TraceKind::Section => String::new(),
+        TraceKind::Path => " kind=\"path\"".to_string(),
+        TraceKind::Alt => " kind=\"alt\"".to_string(),
+        TraceKind::Loop(bound) => format!(" kind=\"loop\" bound=\"{}\"", bound),
+    }
+}
+
+fn write_node(
+    s: &mut String,
+    id: &str,
+    parent: &str,
+    srp_type: &str,
+    trace: &Trace,
+    (x, y, height): (f64, f64, f64),
+    scale: f64,
+) {
+    let style = match trace.kind {
+        TraceKind::Section if srp_type == "trace" => "fillColor=#d5e8d4;strokeColor=#82b366;",
+        TraceKind::Section => "fillColor=#dae8fc;strokeColor=#6c8ebf;",
+        _ => "fillColor=none;dashed=1;",
+    };
+    let best = trace
+        .best
+        .map_or_else(String::new, |best| format!(" best=\"{}\"", best));
+    writeln!(
+        s,
+        "        <object label=\"{}\" srp_type=\"{}\"{}{} id=\"{}\">",
+        escape(&trace.id),
+        srp_type,
+        kind_attrs(&trace.kind),
+        best,
+        id
+    )
+    .unwrap();
+    writeln!(
+        s,
+        "          <mxCell style=\"rounded=0;whiteSpace=wrap;html=1;verticalAlign=top;{}\" vertex=\"1\" parent=\"{}\">",
+        style, parent
+    )
+    .unwrap();
+    writeln!(
+        s,
+        "            <mxGeometry x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" as=\"geometry\" />",
+        x,
+        y,
+        trace.span() as f64 * scale,
+        height
+    )
+    .unwrap();
+    writeln!(s, "          </mxCell>").unwrap();
+    writeln!(s, "        </object>").unwrap();
+
+    let inner_height = (height - 16.0).max(10.0);
+    for (i, inner) in trace.inner.iter().enumerate() {
+        let inner_x = inner.start.saturating_sub(trace.start) as f64 * scale;
+        let inner_id = format!("{}-{}", id, i);
+        write_node(
+            s,
+            &inner_id,
+            id,
+            "node",
+            inner,
+            (inner_x, 14.0, inner_height),
+            scale,
+        );
+    }
+}
+
+// The task set as a draw.io diagram
+pub fn export(tasks: &Tasks) -> String {
+    let max_end = tasks
+        .0
+        .iter()
+        .flat_map(|t| t.all_traces())
+        .map(|t| t.end)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let scale = WIDTH / max_end as f64;
+
+    let mut s = String::new();
+    writeln!(s, "<mxfile host=\"srp\">").unwrap();
+    writeln!(s, "  <diagram name=\"Tasks\" id=\"tasks\">").unwrap();
+    writeln!(s, "    <mxGraphModel grid=\"1\" gridSize=\"10\">").unwrap();
+    writeln!(s, "      <root>").unwrap();
+    writeln!(s, "        <mxCell id=\"0\" />").unwrap();
+    writeln!(s, "        <mxCell id=\"1\" parent=\"0\" />").unwrap();
+
+    let mut y = 0.0;
+    for t in &tasks.0 {
+        let lane = format!("task-{}", escape(&t.id));
+        let height = t.all_traces().count() as f64 * (TRACE_HEIGHT + 10.0) + 10.0;
+        let clock_hz = t
+            .time_base
+            .clock_hz
+            .map_or_else(String::new, |hz| format!(" clock_hz=\"{}\"", hz));
+        let spawns = if t.spawns.is_empty() {
+            String::new()
+        } else {
+            format!(" spawns=\"{}\"", escape(&t.spawns.join(",")))
+        };
+        let periodic = if t.periodic { " periodic=\"true\"" } else { "" };
+        let dispatch = t.dispatch.as_ref().map_or_else(String::new, |d| {
+            format!(
+                " dispatcher=\"{}\" capacity=\"{}\" overhead=\"{}\"",
+                escape(&d.dispatcher),
+                d.capacity,
+                d.overhead
+            )
+        });
+        writeln!(
+            s,
+            "        <object label=\"{}\" srp_type=\"task\" prio=\"{}\" deadline=\"{}\" inter_arrival=\"{}\" unit=\"{}\"{}{}{}{} scale=\"{}\" id=\"{}\">",
+            escape(&t.id),
+            t.prio,
+            t.deadline,
+            t.inter_arrival,
+            t.time_base.unit,
+            clock_hz,
+            spawns,
+            periodic,
+            dispatch,
+            scale,
+            lane
+        )
+        .unwrap();
+        writeln!(
+            s,
+            "          <mxCell style=\"swimlane;horizontal=0;startSize={};\" vertex=\"1\" parent=\"1\">",
+            HEADER
+        )
+        .unwrap();
+        writeln!(
+            s,
+            "            <mxGeometry y=\"{}\" width=\"{}\" height=\"{}\" as=\"geometry\" />",
+            y,
+            HEADER + WIDTH + 20.0,
+            height
+        )
+        .unwrap();
+        writeln!(s, "          </mxCell>").unwrap();
+        writeln!(s, "        </object>").unwrap();
+
+        for (i, trace) in t.all_traces().enumerate() {
+            let x = HEADER + trace.start as f64 * scale;
+            let trace_y = 10.0 + i as f64 * (TRACE_HEIGHT + 10.0);
+            let id = format!("{}-{}", lane, i);
+            write_node(
+                &mut s,
+                &id,
+                &lane,
+                "trace",
+                trace,
+                (x, trace_y, TRACE_HEIGHT),
+                scale,
+            );
+        }
+        y += height;
+    }
+
+    writeln!(s, "      </root>").unwrap();
+    writeln!(s, "    </mxGraphModel>").unwrap();
+    writeln!(s, "  </diagram>").unwrap();
+    writeln!(s, "</mxfile>").unwrap();
+    s
+}
+
+// A diagram element, i.e., an (object wrapped) mxCell and its geometry
+#[derive(Debug, Default)]
+struct Element {
+    line: usize,
+    id: String,
+    parent: String,
+    attrs: HashMap<String, String>,
+    x: f64,
+    y: f64,
+    width: f64,
+}
+
+// Attributes of the tag at the start of s (following the tag name)
+fn attributes(s: &str) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let mut rest = s;
+    while let Some(eq) = rest.find("=\"") {
+        let key = rest[..eq]
+            .split_whitespace()
+            .last()
+            .unwrap_or("")
+            .to_string();
+        let value_start = eq + 2;
+        let Some(len) = rest[value_start..].find('"') else {
+            break;
+        };
+        attrs.insert(key, unescape(&rest[value_start..value_start + len]));
+        rest = &rest[value_start + len + 1..];
+    }
+    attrs
+}
+
+fn elements(xml: &str) -> Vec<Element> {
+    let mut elements: Vec<Element> = vec![];
+    let mut object: Option<HashMap<String, String>> = None;
+    let mut pos = 0;
+    let mut line = 1;
+    while let Some(open) = xml[pos..].find('<') {
+        let start = pos + open + 1;
+        line += xml[pos..start].matches('\n').count();
+        let Some(close) = xml[start..].find('>') else {
+            break;
+        };
+        let tag = &xml[start..start + close];
+        pos = start + close + 1;
+        let name = tag.split_whitespace().next().unwrap_or("");
+        let attrs = attributes(&tag[name.len()..]);
+        match name {
+            "object" | "UserObject" => object = Some(attrs),
+            "/object" | "/UserObject" => object = None,
+            "mxCell" => {
+                let mut all = object.take().unwrap_or_default();
+                let parent = attrs.get("parent").cloned().unwrap_or_default();
+                let id = all
+                    .get("id")
+                    .or(attrs.get("id"))
+                    .cloned()
+                    .unwrap_or_default();
+                for (k, v) in attrs {
+                    all.entry(k).or_insert(v);
+                }
+                elements.push(Element {
+                    line,
+                    id,
+                    parent,
+                    attrs: all,
+                    ..Element::default()
+                });
+            }
+            "mxGeometry" => {
+                if let Some(e) = elements.last_mut() {
+                    let get = |k: &str| attrs.get(k).and_then(|v| v.parse().ok()).unwrap_or(0.0);
+                    e.x = get("x");
+                    e.y = get("y");
+                    e.width = get("width");
+                }
+            }
+            _ => {}
+        }
+    }
+    elements
+}
+
+fn attr<T: std::str::FromStr>(e: &Element, key: &str) -> Result<T, ParseError> {
+    e.attrs
+        .get(key)
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| ParseError::new(e.line, format!("missing or invalid attribute {}", key)))
+}
+
+fn label(e: &Element) -> String {
+    e.attrs
+        .get("label")
+        .or(e.attrs.get("value"))
+        .cloned()
+        .unwrap_or_default()
+}
+
+// The children of element parent of the given srp type, reporting children
+// without srp data (e.g., boxes drawn by hand) rather than silently ignoring them
+fn children<'a>(
+    elements: &'a [Element],
+    parent: &Element,
+    srp_type: &str,
+) -> Result<Vec<&'a Element>, ParseError> {
+    let mut children = vec![];
+    for c in elements.iter().filter(|c| c.parent == parent.id) {
+        match c.attrs.get("srp_type") {
+            Some(t) if t == srp_type => children.push(c),
+            Some(_) => {}
+            None => {
+                return Err(ParseError::new(
+                    c.line,
+                    format!(
+                        "element {:?} in {:?} without srp_type",
+                        label(c),
+                        label(parent)
+                    ),
+                ))
+            }
+        }
+    }
+    Ok(children)
+}
+
+// The trace drawn by element e, starting at start
+fn trace(elements: &[Element], e: &Element, start: Time, scale: f64) -> Result<Trace, ParseError> {
+    let kind = match e.attrs.get("kind").map(|k| k.as_str()) {
+        None | Some("section") => TraceKind::Section,
+        Some("path") => TraceKind::Path,
+        Some("alt") => TraceKind::Alt,
+        Some("loop") => TraceKind::Loop(attr(e, "bound")?),
+        Some(k) => return Err(ParseError::new(e.line, format!("unknown kind {}", k))),
+    };
+    let mut children = children(elements, e, "node")?;
+    children.sort_by(|a, b| a.x.total_cmp(&b.x));
+    let inner = children
+        .into_iter()
+        .map(|c| trace(elements, c, start + (c.x / scale).round() as Time, scale))
+        .collect::<Result<_, _>>()?;
+    Ok(Trace {
+        id: label(e),
+        start,
+        end: start + (e.width / scale).round() as Time,
+        kind,
+        best: attr(e, "best").ok(),
+        inner,
+    })
+}
+
+// Import a task set from a draw.io diagram (as exported by export).
+// Elements without srp data are ignored, unless drawn inside a task lane.
+pub fn import(xml: &str) -> Result<Tasks, ParseError> {
+    let elements = elements(xml);
+    let is = |e: &Element, t: &str| e.attrs.get("srp_type").is_some_and(|v| v == t);
+
+    let mut lanes: Vec<&Element> = elements.iter().filter(|e| is(e, "task")).collect();
+    lanes.sort_by(|a, b| a.y.total_cmp(&b.y));
+    if lanes.is_empty() {
+        return Err(ParseError::new(1, "no task lane (srp_type=\"task\") found"));
+    }
+
+    let tasks = lanes
+        .into_iter()
+        .map(|lane| {
+            let scale: f64 = attr(lane, "scale")?;
+            let mut traces = children(&elements, lane, "trace")?;
+            traces.sort_by(|a, b| a.y.total_cmp(&b.y));
+            let mut traces = traces.into_iter().map(|e| {
+                let start = ((e.x - HEADER) / scale).round().max(0.0) as Time;
+                trace(&elements, e, start, scale)
+            });
+            let trace = traces
+                .next()
+                .ok_or_else(|| ParseError::new(lane.line, "task without trace"))??;
+            Ok(Task {
+                id: label(lane),
+                prio: attr(lane, "prio")?,
+                deadline: attr(lane, "deadline")?,
+                inter_arrival: attr(lane, "inter_arrival")?,
+                trace,
+                traces: traces.collect::<Result<_, _>>()?,
+                time_base: TimeBase {
+                    unit: attr(lane, "unit")?,
+                    clock_hz: attr(lane, "clock_hz").ok(),
+                },
+                spawns: attr::<String>(lane, "spawns")
+                    .map(|s| s.split(',').map(|t| t.to_string()).collect())
+                    .unwrap_or_default(),
+                periodic: attr(lane, "periodic").unwrap_or(false),
+                dispatch: match attr::<String>(lane, "dispatcher") {
+                    Ok(dispatcher) => Some(Dispatch {
+                        dispatcher,
+                        capacity: attr(lane, "capacity")?,
+                        overhead: attr(lane, "overhead")?,
+                    }),
+                    Err(_) => None,
+                },
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Tasks(tasks))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let tasks = crate::task_sets::task_set1();
+        let xml = export(&tasks);
+        assert_eq!(import(&xml).unwrap(), tasks);
+
+        let mut tasks = Tasks::load(&std::path::PathBuf::from("task_sets/task_set3.json")).unwrap();
+        tasks.0[0].traces.push(Trace {
+            id: "T1".to_string(),
+            start: 0,
+            end: 30,
+            kind: TraceKind::Section,
+            best: None,
+            inner: vec![Trace {
+                id: "L".to_string(),
+                start: 5,
+                end: 10,
+                kind: TraceKind::Loop(3),
+                best: Some(1),
+                inner: vec![],
+            }],
+        });
+        tasks.0[1].trace.best = Some(20);
+        tasks.0[2].periodic = true;
+        tasks.0[1].time_base = TimeBase {
+            unit: TimeUnit::Cycles,
+            clock_hz: Some(16_000_000),
+        };
+        assert_eq!(import(&export(&tasks)).unwrap(), tasks);
+    }
+
+    #[test]
+    fn import_edited() {
+        let tasks = crate::task_sets::task_set1();
+        // move the second R1 section of T2 (at 22..28) to 24..30
+        let scale = WIDTH / 30.0;
+        let xml = export(&tasks).replace(
+            &format!("x=\"{}\" y=\"14\" width=\"{}\"", 22.0 * scale, 6.0 * scale),
+            &format!("x=\"{}\" y=\"14\" width=\"{}\"", 24.0 * scale, 6.0 * scale),
+        );
+        let imported = import(&xml).unwrap();
+        let cs = &imported.0[1].trace.inner[1];
+        assert_eq!((cs.start, cs.end), (24, 30));
+
+        // a box drawn by hand inside a lane, and a diagram without lanes
+        let xml = export(&tasks).replace(
+            "        <object label=\"T1\" srp_type=\"trace\"",
+            "        <mxCell id=\"box\" value=\"T1\" vertex=\"1\" parent=\"task-T1\" />\n        <object label=\"T1\" srp_type=\"trace\"",
+        );
+        assert!(import(&xml)
+            .unwrap_err()
+            .message
+            .contains("without srp_type"));
+
+        // the diagram shipped with the repository, exported from task_set1.json
+        let xml = std::fs::read_to_string("task_set1.drawio").unwrap();
+        assert_eq!(import(&xml).unwrap(), tasks);
+        assert_eq!(export(&tasks), xml);
+    }
+}
@@ -0,0 +1,28 @@
+// Escaping of text in XML (draw.io diagrams, JUnit and SARIF reports)
+
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub fn unescape(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn escape_round_trip() {
+        let s = r#"a<b && "c" > d"#;
+        assert_eq!(escape(s), "a&lt;b &amp;&amp; &quot;c&quot; &gt; d");
+        assert_eq!(unescape(&escape(s)), s);
+        assert_eq!(unescape(&escape("&quot;")), "&quot;");
+    }
+}
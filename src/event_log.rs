@@ -0,0 +1,331 @@
+// Import of task sets from event logs
+//
+// An event log holds one event per line, `<ts> enter T2`, `<ts> lock R1`,
+// `<ts> unlock R1` and `<ts> exit T2`, with timestamps in increasing order.
+// Empty lines and lines starting with `#` are ignored.
+
+use crate::common::*;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Enter(String),
+    Exit(String),
+    Lock(String),
+    Unlock(String),
+}
+
+// A parsed log line, (line number, timestamp, event)
+pub type LogEntry = (usize, Time, Event);
+
+// Parse a line oriented event log
+pub fn parse(log: &str) -> Result<Vec<LogEntry>, ParseError> {
+    let mut entries = vec![];
+    let mut last = 0;
+    for (nr, line) in log.lines().enumerate() {
+        let nr = nr + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let [ts, kind, id] = words[..] else {
+            return Err(ParseError::new(nr, "expected `<ts> <event> <id>`"));
+        };
+        let ts: Time = ts
+            .parse()
+            .map_err(|_| ParseError::new(nr, format!("invalid timestamp {}", ts)))?;
+        if ts < last {
+            return Err(ParseError::new(nr, "timestamps must be increasing"));
+        }
+        last = ts;
+        let id = id.to_string();
+        let event = match kind {
+            "enter" => Event::Enter(id),
+            "exit" => Event::Exit(id),
+            "lock" => Event::Lock(id),
+            "unlock" => Event::Unlock(id),
+            _ => return Err(ParseError::new(nr, format!("unknown event {}", kind))),
+        };
+        entries.push((nr, ts, event));
+    }
+    Ok(entries)
+}
+
+// A running task instance
+struct Instance {
+    id: String,
+    enter: Time,
+    // time spent in preempting instances
+    preempted: Time,
+    // the task trace and currently held critical sections
+    sections: Vec<Trace>,
+}
+
+impl Instance {
+    // Execution time of the instance at wall clock time ts
+    fn local(&self, ts: Time) -> Time {
+        ts - self.enter - self.preempted
+    }
+}
+
+// The observations of a task
+#[derive(Default)]
+struct Observed {
+    traces: Vec<Trace>,
+    arrivals: Vec<Time>,
+}
+
+// Reconstruct the traces of each task instance of the log.
+// Time spent in preempting instances is excluded, so trace timestamps are
+// relative to the execution of the instance itself.
+pub fn reconstruct(entries: &[LogEntry]) -> Result<Vec<(String, Time, Trace)>, ParseError> {
+    let mut running: Vec<Instance> = vec![];
+    let mut instances = vec![];
+
+    for (nr, ts, event) in entries {
+        let (nr, ts) = (*nr, *ts);
+        match event {
+            Event::Enter(id) => {
+                if running.iter().any(|i| &i.id == id) {
+                    return Err(ParseError::new(nr, format!("task {} entered twice", id)));
+                }
+                running.push(Instance {
+                    id: id.clone(),
+                    enter: ts,
+                    preempted: 0,
+                    sections: vec![Trace {
+                        id: id.clone(),
+                        start: 0,
+                        end: 0,
+                        kind: TraceKind::Section,
+                        best: None,
+                        inner: vec![],
+                    }],
+                })
+            }
+            Event::Exit(id) => {
+                let mut instance = match running.pop() {
+                    Some(i) if &i.id == id => i,
+                    _ => return Err(ParseError::new(nr, format!("task {} is not running", id))),
+                };
+                if instance.sections.len() > 1 {
+                    return Err(ParseError::new(
+                        nr,
+                        format!("task {} exits holding a resource", id),
+                    ));
+                }
+                let mut trace = instance.sections.pop().unwrap();
+                trace.end = instance.local(ts);
+                if let Some(preempted) = running.last_mut() {
+                    preempted.preempted += ts - instance.enter;
+                }
+                instances.push((id.clone(), instance.enter, trace));
+            }
+            Event::Lock(r) => {
+                let instance = running
+                    .last_mut()
+                    .ok_or_else(|| ParseError::new(nr, "lock outside of task"))?;
+                if instance.sections.iter().any(|cs| &cs.id == r) {
+                    return Err(ParseError::new(nr, format!("resource {} locked twice", r)));
+                }
+                let start = instance.local(ts);
+                instance.sections.push(Trace {
+                    id: r.clone(),
+                    start,
+                    end: start,
+                    kind: TraceKind::Section,
+                    best: None,
+                    inner: vec![],
+                });
+            }
+            Event::Unlock(r) => {
+                let instance = running
+                    .last_mut()
+                    .ok_or_else(|| ParseError::new(nr, "unlock outside of task"))?;
+                if instance.sections.len() < 2 || &instance.sections.last().unwrap().id != r {
+                    return Err(ParseError::new(
+                        nr,
+                        format!("resource {} is not the innermost held", r),
+                    ));
+                }
+                let mut cs = instance.sections.pop().unwrap();
+                cs.end = instance.local(ts);
+                instance.sections.last_mut().unwrap().inner.push(cs);
+            }
+        }
+    }
+
+    match running.last() {
+        Some(i) => Err(ParseError::new(
+            entries.last().map_or(0, |e| e.0),
+            format!("task {} did not exit", i.id),
+        )),
+        None => Ok(instances),
+    }
+}
+
+// The traces among observed that hold the worst case, i.e., the trace with the
+// largest wcet and for each resource the trace with the longest critical section.
+fn worst_case(traces: Vec<Trace>) -> (Trace, Vec<Trace>) {
+    let mut cs_max: HashMap<String, (Time, usize)> = HashMap::new();
+    let mut keep = vec![];
+    for (i, trace) in traces.iter().enumerate() {
+        let task = Task {
+            trace: trace.clone(),
+            ..Task::default()
+        };
+        for (r, wcet) in task.critical_sections() {
+            let max = cs_max.entry(r).or_insert((wcet, i));
+            if wcet > max.0 {
+                *max = (wcet, i);
+            }
+        }
+    }
+    let worst = (0..traces.len())
+        .max_by_key(|i| (traces[*i].wcet(), std::cmp::Reverse(*i)))
+        .unwrap();
+    keep.push(worst);
+    for (_, i) in cs_max.into_values() {
+        if !keep.contains(&i) {
+            keep.push(i);
+        }
+    }
+    keep[1..].sort();
+
+    let mut traces: Vec<Option<Trace>> = traces.into_iter().map(Some).collect();
+    let mut keep = keep.into_iter().map(|i| traces[i].take().unwrap());
+    let trace = keep.next().unwrap();
+    (trace, keep.collect())
+}
+
+// Import a task set from an event log.
+// Priorities are unknown (0, as idle), to be merged from the structure of the
+// application, as the analysis rejects a set without priorities. Deadline and
+// inter-arrival are the shortest observed inter-arrival of each task, or period
+// if the task was observed once (an error without period). The shortest
+// observed instance gives the best case of the task body.
+pub fn import(log: &str, time_base: TimeBase, period: Option<Time>) -> Result<Tasks, ParseError> {
+    let entries = parse(log)?;
+    let mut instances = reconstruct(&entries)?;
+    instances.sort_by_key(|(_, enter, _)| *enter);
+
+    let mut order = vec![];
+    let mut observed: HashMap<String, Observed> = HashMap::new();
+    for (id, enter, trace) in instances {
+        if !observed.contains_key(&id) {
+            order.push(id.clone());
+        }
+        let o = observed.entry(id).or_default();
+        o.traces.push(trace);
+        o.arrivals.push(enter);
+    }
+
+    Ok(Tasks(
+        order
+            .into_iter()
+            .map(|id| {
+                let o = observed.remove(&id).unwrap();
+                let mut arrivals = o.arrivals;
+                arrivals.sort();
+                let inter_arrival = arrivals
+                    .windows(2)
+                    .map(|w| w[1] - w[0])
+                    .min()
+                    .or(period)
+                    .ok_or_else(|| {
+                        let line = entries
+                            .iter()
+                            .find(|(_, _, e)| *e == Event::Enter(id.clone()))
+                            .map_or(0, |e| e.0);
+                        ParseError::new(
+                            line,
+                            format!("task {} observed once, its period is unknown", id),
+                        )
+                    })?;
+                let best = o.traces.iter().map(|t| t.wcet()).min();
+                let (mut trace, traces) = worst_case(o.traces);
+                trace.best = best.filter(|best| *best < trace.wcet());
+                Ok(Task {
+                    id,
+                    prio: 0,
+                    deadline: inter_arrival,
+                    inter_arrival,
+                    trace,
+                    traces,
+                    time_base,
+                    spawns: vec![],
+                    periodic: false,
+                    dispatch: None,
+                })
+            })
+            .collect::<Result<_, _>>()?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LOG: &str = "
+        # T1 preempted by T2 while holding R1
+        0 enter T1
+        2 lock R1
+        5 enter T2
+        6 lock R2
+        8 unlock R2
+        9 exit T2
+        10 unlock R1
+        12 exit T1
+        100 enter T1
+        101 lock R2
+        103 lock R1
+        104 unlock R1
+        110 unlock R2
+        111 exit T1
+    ";
+
+    #[test]
+    fn import_log() {
+        assert_eq!(
+            import(LOG, TimeBase::default(), None),
+            Err(ParseError::new(
+                5,
+                "task T2 observed once, its period is unknown"
+            ))
+        );
+        let tasks = import(LOG, TimeBase::default(), Some(50)).unwrap();
+        assert_eq!(tasks.0.len(), 2);
+
+        let t1 = &tasks.0[0];
+        assert_eq!(t1.id, "T1");
+        assert_eq!(t1.inter_arrival, 100);
+        // the second instance has the larger wcet (11)
+        assert_eq!(t1.wcet(), 11);
+        // and the first the shorter (12 - 4 preemption)
+        assert_eq!(t1.bcet(), 8);
+        assert_eq!(t1.trace.inner[0].id, "R2");
+        // the first instance holds R1 for 4 (8 - 4 preemption)
+        assert_eq!(t1.traces.len(), 1);
+        assert_eq!(t1.traces[0].inner[0].wcet(), 4);
+        assert_eq!(t1.critical_sections().get("R1"), Some(&4));
+
+        let t2 = &tasks.0[1];
+        assert_eq!(t2.wcet(), 4);
+        assert_eq!(t2.inter_arrival, 50);
+        assert_eq!(tasks.response_time(), Err(AnalysisError::NoPriorities));
+    }
+
+    #[test]
+    fn import_errors() {
+        assert_eq!(
+            import("0 enter T1\n1 unlock R1\n", TimeBase::default(), None),
+            Err(ParseError::new(2, "resource R1 is not the innermost held"))
+        );
+        assert_eq!(
+            import("0 enter T1\n", TimeBase::default(), None),
+            Err(ParseError::new(1, "task T1 did not exit"))
+        );
+        assert!(parse("1 enter T1\n0 exit T1").is_err());
+    }
+}
@@ -0,0 +1,272 @@
+// Random task sets for experiments
+//
+// Utilizations are drawn by UUniFast, periods log-uniform, and priorities
+// assigned rate monotonic. Each task holds a number of critical sections on
+// random resources, possibly nested. Generation is seeded, so a seed always
+// gives the same task set.
+
+use crate::common::*;
+
+// SplitMix64, small and good enough for experiments
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1)
+    pub fn f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Uniform in [lo, hi]
+    pub fn range(&mut self, lo: u64, hi: u64) -> u64 {
+        if hi <= lo {
+            return lo;
+        }
+        lo + self.next_u64() % (hi - lo + 1)
+    }
+}
+
+// The most tasks of a set, each at a distinct priority
+pub const MAX_TASKS: usize = u8::MAX as usize;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenConfig {
+    // number of tasks, 1 to MAX_TASKS
+    pub tasks: usize,
+    // total utilization
+    pub utilization: f64,
+    // range of periods (inter-arrival and deadline)
+    pub period_min: Time,
+    pub period_max: Time,
+    pub resources: usize,
+    // maximum number of (outermost) critical sections per task
+    pub max_sections: usize,
+    // maximum nesting depth of critical sections
+    pub max_nesting: usize,
+    // range of critical section lengths, as a fraction of the enclosing section
+    pub cs_min: f64,
+    pub cs_max: f64,
+}
+
+impl Default for GenConfig {
+    fn default() -> Self {
+        GenConfig {
+            tasks: 5,
+            utilization: 0.5,
+            period_min: 100,
+            period_max: 10_000,
+            resources: 3,
+            max_sections: 2,
+            max_nesting: 2,
+            cs_min: 0.05,
+            cs_max: 0.3,
+        }
+    }
+}
+
+// UUniFast, n utilizations summing to total (none for n = 0)
+pub fn uunifast(rng: &mut Rng, n: usize, total: f64) -> Vec<f64> {
+    if n == 0 {
+        return vec![];
+    }
+    let mut utils = vec![];
+    let mut sum = total;
+    for i in 1..n {
+        let next = sum * rng.f64().powf(1.0 / (n - i) as f64);
+        utils.push(sum - next);
+        sum = next;
+    }
+    utils.push(sum);
+    utils
+}
+
+// Log-uniform in [min, max]
+fn log_uniform(rng: &mut Rng, min: Time, max: Time) -> Time {
+    let (lo, hi) = ((min.max(1) as f64).ln(), (max.max(min).max(1) as f64).ln());
+    (lo + rng.f64() * (hi - lo)).exp().round() as Time
+}
+
+impl GenConfig {
+    fn cs_length(&self, rng: &mut Rng, outer: Time) -> Time {
+        let fraction = self.cs_min + rng.f64() * (self.cs_max - self.cs_min);
+        ((outer as f64 * fraction).round() as Time).clamp(1, outer)
+    }
+
+    // Critical sections within [start, end], on resources not held
+    fn sections(
+        &self,
+        rng: &mut Rng,
+        start: Time,
+        end: Time,
+        held: &mut Vec<String>,
+        n: usize,
+    ) -> Vec<Trace> {
+        let mut inner = vec![];
+        if n == 0 || held.len() >= self.max_nesting || end <= start {
+            return inner;
+        }
+        // one slot per section, keeping sections apart
+        let slot = (end - start) / n as Time;
+        for i in 0..n as Time {
+            let free: Vec<usize> = (1..=self.resources)
+                .filter(|r| !held.contains(&format!("R{}", r)))
+                .collect();
+            if free.is_empty() || slot == 0 {
+                break;
+            }
+            let r = format!("R{}", free[rng.range(0, free.len() as u64 - 1) as usize]);
+            let len = self.cs_length(rng, slot);
+            let cs_start = start + i * slot + rng.range(0, slot - len);
+            held.push(r.clone());
+            // nest a single section, with probability 1/2
+            let nested = if held.len() < self.max_nesting && rng.range(0, 1) == 1 {
+                self.sections(rng, cs_start, cs_start + len, held, 1)
+            } else {
+                vec![]
+            };
+            held.pop();
+            inner.push(Trace {
+                id: r,
+                start: cs_start,
+                end: cs_start + len,
+                kind: TraceKind::Section,
+                best: None,
+                inner: nested,
+            });
+        }
+        inner
+    }
+
+    // An error for a config that cannot be generated, as each task needs a
+    // distinct priority
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_TASKS).contains(&self.tasks) {
+            return Err(format!(
+                "expected 1 to {} tasks, got {}",
+                MAX_TASKS, self.tasks
+            ));
+        }
+        Ok(())
+    }
+
+    // A random task set, drawing from rng
+    pub fn generate(&self, rng: &mut Rng) -> Result<Tasks, String> {
+        self.validate()?;
+        let utils = uunifast(rng, self.tasks, self.utilization);
+        let mut tasks: Vec<Task> = utils
+            .into_iter()
+            .enumerate()
+            .map(|(i, u)| {
+                let id = format!("T{}", i + 1);
+                let period = log_uniform(rng, self.period_min, self.period_max);
+                let wcet = ((u * period as f64).round() as Time).max(1);
+                let n = if self.resources == 0 {
+                    0
+                } else {
+                    rng.range(0, self.max_sections as u64) as usize
+                };
+                let inner = self.sections(rng, 0, wcet, &mut vec![], n);
+                Task {
+                    id: id.clone(),
+                    prio: 0,
+                    deadline: period,
+                    inter_arrival: period,
+                    trace: Trace {
+                        id,
+                        start: 0,
+                        end: wcet,
+                        kind: TraceKind::Section,
+                        best: None,
+                        inner,
+                    },
+                    traces: vec![],
+                    time_base: TimeBase::default(),
+                    spawns: vec![],
+                    periodic: false,
+                    dispatch: None,
+                }
+            })
+            .collect();
+
+        // rate monotonic, the shortest period at the highest priority
+        let mut order: Vec<usize> = (0..tasks.len()).collect();
+        order.sort_by_key(|i| std::cmp::Reverse(tasks[*i].inter_arrival));
+        for (p, i) in order.into_iter().enumerate() {
+            tasks[i].prio = (p + 1) as u8;
+        }
+        Ok(Tasks(tasks))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Sections lie within their parent, and no resource is claimed twice
+    fn valid(trace: &Trace, held: &mut Vec<String>) -> bool {
+        trace.inner.iter().all(|cs| {
+            let ok = cs.start >= trace.start && cs.end <= trace.end && !held.contains(&cs.id);
+            held.push(cs.id.clone());
+            let ok = ok && valid(cs, held);
+            held.pop();
+            ok
+        })
+    }
+
+    #[test]
+    fn generate_seeded() {
+        let config = GenConfig {
+            tasks: 10,
+            utilization: 0.7,
+            ..GenConfig::default()
+        };
+        let tasks = config.generate(&mut Rng::new(7)).unwrap();
+        assert_eq!(tasks, config.generate(&mut Rng::new(7)).unwrap());
+        assert_ne!(tasks, config.generate(&mut Rng::new(8)).unwrap());
+
+        let utilization: f64 = tasks
+            .0
+            .iter()
+            .map(|t| t.wcet() as f64 / t.inter_arrival as f64)
+            .sum();
+        assert!((utilization - 0.7).abs() < 0.05);
+        for t in &tasks.0 {
+            assert!(valid(&t.trace, &mut vec![t.id.clone()]));
+        }
+        let mut prios: Vec<u8> = tasks.0.iter().map(|t| t.prio).collect();
+        prios.sort();
+        assert_eq!(prios, (1..=10).collect::<Vec<u8>>());
+        assert!(uunifast(&mut Rng::new(7), 0, 0.7).is_empty());
+    }
+
+    #[test]
+    fn generate_bounds() {
+        let config = |tasks| GenConfig {
+            tasks,
+            ..GenConfig::default()
+        };
+        assert_eq!(
+            config(0).generate(&mut Rng::new(7)),
+            Err("expected 1 to 255 tasks, got 0".to_string())
+        );
+        assert_eq!(
+            config(MAX_TASKS + 1).generate(&mut Rng::new(7)),
+            Err("expected 1 to 255 tasks, got 256".to_string())
+        );
+        assert_eq!(config(1).generate(&mut Rng::new(7)).unwrap().0.len(), 1);
+        let tasks = config(MAX_TASKS).generate(&mut Rng::new(7)).unwrap();
+        assert_eq!(tasks.0.iter().map(|t| t.prio).max(), Some(u8::MAX));
+    }
+}
@@ -0,0 +1,258 @@
+// Task/resource graphs, rendered in Graphviz DOT or Mermaid syntax
+
+use crate::common::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(GraphFormat::Dot),
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            _ => Err(format!("unknown graph format {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Task,
+    Resource,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub id: String,
+    pub label: String,
+    pub shape: Shape,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub label: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+// helper functions
+
+// A node identifier valid in both DOT and Mermaid
+fn node_id(prefix: &str, id: &str) -> String {
+    let id: String = id
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}_{}", prefix, id)
+}
+
+// A label as a DOT quoted string, escaping quotes and backslashes
+fn dot_label(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// A label as a Mermaid quoted string, quotes given as an entity code
+fn mermaid_label(label: &str) -> String {
+    label.replace('"', "#quot;")
+}
+
+fn resource_node(r: &str, ceiling: Option<u8>) -> Node {
+    Node {
+        id: node_id("r", r),
+        label: match ceiling {
+            Some(c) => format!("{} (ceiling {})", r, c),
+            None => r.to_string(),
+        },
+        shape: Shape::Resource,
+    }
+}
+
+// Collect (outer, inner) pairs of directly nested critical sections
+fn nesting(outer: Option<&str>, trace: &Trace, pairs: &mut BTreeSet<(String, String)>) {
+    for inner in &trace.inner {
+        if inner.kind.is_section() {
+            if let Some(outer) = outer {
+                pairs.insert((outer.to_string(), inner.id.clone()));
+            }
+            nesting(Some(&inner.id), inner, pairs);
+        } else {
+            nesting(outer, inner, pairs);
+        }
+    }
+}
+
+impl Graph {
+    // Bipartite graph of tasks and the resources they lock, edges labelled
+    // with the longest critical section, resources labelled with their ceiling
+    pub fn resources(tasks: &Tasks) -> Graph {
+        let table = tasks.resource_table();
+        let mut graph = Graph::default();
+        let mut resources = BTreeSet::new();
+        for t in &tasks.0 {
+            graph.nodes.push(Node {
+                id: node_id("t", &t.id),
+                label: format!("{} (prio {})", t.id, t.prio),
+                shape: Shape::Task,
+            });
+            let cs: BTreeMap<String, Time> = t.critical_sections().into_iter().collect();
+            for (r, wcet) in cs {
+                graph.edges.push(Edge {
+                    from: node_id("t", &t.id),
+                    to: node_id("r", &r),
+                    label: wcet.to_string(),
+                });
+                resources.insert(r);
+            }
+        }
+        for r in resources {
+            graph.nodes.push(resource_node(&r, table.ceiling(&r)));
+        }
+        graph
+    }
+
+    // Graph of resources, with an edge from each resource to the resources
+    // locked (directly) nested inside its critical sections
+    pub fn nesting(tasks: &Tasks) -> Graph {
+        let table = tasks.resource_table();
+        let mut pairs = BTreeSet::new();
+        let mut resources = BTreeSet::new();
+        for t in &tasks.0 {
+            for trace in t.all_traces() {
+                nesting(None, trace, &mut pairs);
+            }
+            resources.extend(t.critical_sections().into_keys());
+        }
+        Graph {
+            nodes: resources
+                .iter()
+                .map(|r| resource_node(r, table.ceiling(r)))
+                .collect(),
+            edges: pairs
+                .into_iter()
+                .map(|(outer, inner)| Edge {
+                    from: node_id("r", &outer),
+                    to: node_id("r", &inner),
+                    label: String::new(),
+                })
+                .collect(),
+        }
+    }
+
+    // The graph in Graphviz DOT syntax
+    pub fn dot(&self) -> String {
+        let mut s = String::new();
+        writeln!(s, "digraph G {{").unwrap();
+        writeln!(s, "    rankdir=LR;").unwrap();
+        for n in &self.nodes {
+            let shape = match n.shape {
+                Shape::Task => "box",
+                Shape::Resource => "ellipse",
+            };
+            writeln!(
+                s,
+                "    {} [label=\"{}\", shape={}];",
+                n.id,
+                dot_label(&n.label),
+                shape
+            )
+            .unwrap();
+        }
+        for e in &self.edges {
+            if e.label.is_empty() {
+                writeln!(s, "    {} -> {};", e.from, e.to).unwrap();
+            } else {
+                writeln!(
+                    s,
+                    "    {} -> {} [label=\"{}\"];",
+                    e.from,
+                    e.to,
+                    dot_label(&e.label)
+                )
+                .unwrap();
+            }
+        }
+        writeln!(s, "}}").unwrap();
+        s
+    }
+
+    // The graph in Mermaid syntax
+    pub fn mermaid(&self) -> String {
+        let mut s = String::new();
+        writeln!(s, "graph LR;").unwrap();
+        for n in &self.nodes {
+            match n.shape {
+                Shape::Task => writeln!(s, "{}[\"{}\"];", n.id, mermaid_label(&n.label)).unwrap(),
+                Shape::Resource => {
+                    writeln!(s, "{}((\"{}\"));", n.id, mermaid_label(&n.label)).unwrap()
+                }
+            }
+        }
+        for e in &self.edges {
+            if e.label.is_empty() {
+                writeln!(s, "{} --> {};", e.from, e.to).unwrap();
+            } else {
+                writeln!(s, "{} -- {} --> {};", e.from, e.label, e.to).unwrap();
+            }
+        }
+        s
+    }
+
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.dot(),
+            GraphFormat::Mermaid => self.mermaid(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn graph_task_set1() {
+        let tasks = crate::task_sets::task_set1();
+
+        let dot = Graph::resources(&tasks).dot();
+        assert!(dot.contains("t_T2 [label=\"T2 (prio 2)\", shape=box];"));
+        assert!(dot.contains("r_R2 [label=\"R2 (ceiling 3)\", shape=ellipse];"));
+        assert!(dot.contains("t_T2 -> r_R1 [label=\"10\"];"));
+
+        let mermaid = Graph::nesting(&tasks).mermaid();
+        assert_eq!(
+            mermaid,
+            "graph LR;\n\
+             r_R1((\"R1 (ceiling 2)\"));\n\
+             r_R2((\"R2 (ceiling 3)\"));\n\
+             r_R1 --> r_R2;\n"
+        );
+    }
+
+    #[test]
+    fn graph_quoted_ids() {
+        let mut tasks = crate::task_sets::task_set1();
+        tasks.0[0].id = r#"T"1\"#.to_string();
+        tasks.0[1].trace.inner[0].id = r#"R"1"#.to_string();
+
+        let dot = Graph::resources(&tasks).dot();
+        assert!(dot.contains(r#"t_T_1_ [label="T\"1\\ (prio 1)", shape=box];"#));
+        assert!(dot.contains(r#"r_R_1 [label="R\"1 (ceiling 2)", shape=ellipse];"#));
+
+        let mermaid = Graph::resources(&tasks).mermaid();
+        assert!(mermaid.contains(r#"t_T_1_["T#quot;1\ (prio 1)"];"#));
+    }
+}
@@ -1,7 +1,25 @@
 // srp
 
+pub mod acceptance;
 pub mod analysis;
+pub mod chain;
 pub mod common;
+pub mod diff;
+pub mod drawio;
+pub mod escape;
+pub mod event_log;
+pub mod generate;
+pub mod graph;
+pub mod lint;
+pub mod merge;
+pub mod model;
+pub mod policy;
+pub mod property;
+pub mod report;
+pub mod resources;
+pub mod rtic;
+pub mod simulator;
 // pub mod histogram;
 pub mod task_sets;
+pub mod time;
 // pub mod plot;
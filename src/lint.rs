@@ -0,0 +1,365 @@
+// Lints for task set design smells
+//
+// Each rule has a name and a default severity, which can be overridden by a
+// LintConfig. Rules at severity allow are not checked.
+
+use crate::common::*;
+use crate::resources::ResourceTable;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Allow,
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Severity::Allow => "allow",
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(Severity::Allow),
+            "info" => Ok(Severity::Info),
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(format!("unknown severity {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LintConfig {
+    // severity per rule name, overriding the default
+    pub rules: HashMap<String, Severity>,
+    // critical sections longer than this fraction of the shortest deadline
+    // of a task they may block are reported
+    pub cs_fraction: f64,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            rules: HashMap::new(),
+            cs_fraction: 0.1,
+        }
+    }
+}
+
+impl LintConfig {
+    // A config from JSON, rejecting rules not in RULES
+    pub fn from_json(s: &str) -> Result<LintConfig, String> {
+        let config: LintConfig = serde_json::from_str(s).map_err(|e| e.to_string())?;
+        let mut names: Vec<&String> = config.rules.keys().collect();
+        names.sort();
+        if let Some(name) = names
+            .into_iter()
+            .find(|name| !RULES.iter().any(|r| r.name == name.as_str()))
+        {
+            return Err(format!("unknown rule {}", name));
+        }
+        Ok(config)
+    }
+
+    pub fn severity(&self, rule: &Rule) -> Severity {
+        self.rules.get(rule.name).copied().unwrap_or(rule.severity)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Finding {
+    pub rule: String,
+    pub severity: Severity,
+    pub task: Option<String>,
+    pub resource: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.rule, self.message)
+    }
+}
+
+// What a rule checks
+pub struct Context<'a> {
+    pub tasks: &'a Tasks,
+    pub resources: ResourceTable,
+    pub config: &'a LintConfig,
+}
+
+// A violation, (task, resource, message)
+pub type Violation = (Option<String>, Option<String>, String);
+
+pub struct Rule {
+    pub name: &'static str,
+    pub severity: Severity,
+    pub description: &'static str,
+    pub check: fn(&Context) -> Vec<Violation>,
+}
+
+pub const RULES: &[Rule] = &[
+    Rule {
+        name: "unnecessary-lock",
+        severity: Severity::Info,
+        description: "a lock on a resource whose ceiling equals the priority of the task",
+        check: unnecessary_lock,
+    },
+    Rule {
+        name: "single-user",
+        severity: Severity::Info,
+        description: "a resource used by a single task only",
+        check: single_user,
+    },
+    Rule {
+        name: "long-critical-section",
+        severity: Severity::Warning,
+        description:
+            "a critical section longer than cs_fraction of the shortest deadline it may block",
+        check: long_critical_section,
+    },
+    Rule {
+        name: "deadline-exceeds-inter-arrival",
+        severity: Severity::Error,
+        description: "a deadline longer than the inter-arrival time of the task",
+        check: deadline_exceeds_inter_arrival,
+    },
+    Rule {
+        name: "equal-priority",
+        severity: Severity::Warning,
+        description:
+            "tasks with identical priorities, ordered only by their (unmodelled) vector index",
+        check: equal_priority,
+    },
+];
+
+// rules
+
+fn unnecessary_lock(cx: &Context) -> Vec<Violation> {
+    let mut found = vec![];
+    for r in &cx.resources.0 {
+        if r.users.len() < 2 {
+            // reported by single-user
+            continue;
+        }
+        for u in r.users.iter().filter(|u| u.prio == r.ceiling) {
+            found.push((
+                Some(u.task.clone()),
+                Some(r.id.clone()),
+                format!(
+                    "task {} locks {} at its own priority {} (ceiling), the lock is unnecessary",
+                    u.task, r.id, u.prio
+                ),
+            ));
+        }
+    }
+    found
+}
+
+fn single_user(cx: &Context) -> Vec<Violation> {
+    cx.resources
+        .0
+        .iter()
+        .filter(|r| r.users.len() == 1)
+        .map(|r| {
+            let task = &r.users[0].task;
+            (
+                Some(task.clone()),
+                Some(r.id.clone()),
+                format!(
+                    "resource {} is only used by task {}, and could be local",
+                    r.id, task
+                ),
+            )
+        })
+        .collect()
+}
+
+fn long_critical_section(cx: &Context) -> Vec<Violation> {
+    let mut found = vec![];
+    for r in &cx.resources.0 {
+        for u in &r.users {
+            // the tasks that may be blocked by the critical section
+            let deadline = cx
+                .tasks
+                .0
+                .iter()
+                .filter(|t| t.prio > u.prio && t.prio <= r.ceiling)
+                .min_by_key(|t| t.deadline);
+            if let Some(t) = deadline {
+                let limit = t.deadline as f64 * cx.config.cs_fraction;
+                if u.critical_section as f64 > limit {
+                    found.push((
+                        Some(u.task.clone()),
+                        Some(r.id.clone()),
+                        format!(
+                            "critical section of {} on {} ({}) exceeds {} of the deadline of {} ({})",
+                            u.task, r.id, u.critical_section, cx.config.cs_fraction, t.id, t.deadline
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+    found
+}
+
+fn deadline_exceeds_inter_arrival(cx: &Context) -> Vec<Violation> {
+    cx.tasks
+        .0
+        .iter()
+        .filter(|t| t.deadline > t.inter_arrival)
+        .map(|t| {
+            (
+                Some(t.id.clone()),
+                None,
+                format!(
+                    "task {} has deadline {} exceeding its inter-arrival {}",
+                    t.id, t.deadline, t.inter_arrival
+                ),
+            )
+        })
+        .collect()
+}
+
+fn equal_priority(cx: &Context) -> Vec<Violation> {
+    let mut found = vec![];
+    for (i, t) in cx.tasks.0.iter().enumerate() {
+        for t1 in &cx.tasks.0[i + 1..] {
+            if t.prio == t1.prio {
+                found.push((
+                    Some(t1.id.clone()),
+                    None,
+                    format!(
+                        "tasks {} and {} share priority {}, ties are broken by vector index",
+                        t.id, t1.id, t.prio
+                    ),
+                ));
+            }
+        }
+    }
+    found
+}
+
+impl Tasks {
+    // Check the task set against all rules enabled by config, in the common
+    // time base of the task set
+    pub fn lint(&self, config: &LintConfig) -> Result<Vec<Finding>, AnalysisError> {
+        let tasks = self.normalize()?;
+        let cx = Context {
+            tasks: &tasks,
+            resources: tasks.resource_table(),
+            config,
+        };
+        let mut findings = vec![];
+        for rule in RULES {
+            let severity = config.severity(rule);
+            if severity == Severity::Allow {
+                continue;
+            }
+            for (task, resource, message) in (rule.check)(&cx) {
+                findings.push(Finding {
+                    rule: rule.name.to_string(),
+                    severity,
+                    task,
+                    resource,
+                    message,
+                });
+            }
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn rules(findings: &[Finding]) -> Vec<(&str, Option<&str>)> {
+        findings
+            .iter()
+            .map(|f| (f.rule.as_str(), f.resource.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn lint_set2() {
+        let mut tasks = Tasks::load(&PathBuf::from("task_sets/task_set2.json")).unwrap();
+        tasks.0[1].prio = 1;
+        tasks.0[1].deadline = 200;
+        let findings = tasks.lint(&LintConfig::default()).unwrap();
+        assert_eq!(
+            rules(&findings),
+            vec![
+                ("unnecessary-lock", Some("R3")),
+                ("single-user", Some("R1")),
+                ("single-user", Some("R2")),
+                ("long-critical-section", Some("R3")),
+                ("deadline-exceeds-inter-arrival", None),
+                ("equal-priority", None),
+            ]
+        );
+
+        let config = LintConfig {
+            rules: [
+                ("single-user".to_string(), Severity::Allow),
+                ("equal-priority".to_string(), Severity::Error),
+            ]
+            .into_iter()
+            .collect(),
+            cs_fraction: 0.5,
+        };
+        let findings = tasks.lint(&config).unwrap();
+        assert_eq!(
+            rules(&findings),
+            vec![
+                ("unnecessary-lock", Some("R3")),
+                ("deadline-exceeds-inter-arrival", None),
+                ("equal-priority", None),
+            ]
+        );
+        assert_eq!(findings[2].severity, Severity::Error);
+
+        // a config file naming a rule that does not exist is rejected
+        let json = r#"{"rules": {"equal-priority": "error"}, "cs_fraction": 0.5}"#;
+        assert_eq!(
+            LintConfig::from_json(json)
+                .unwrap()
+                .rules
+                .get("equal-priority"),
+            Some(&Severity::Error)
+        );
+        let json = r#"{"rules": {"equal-priority": "error", "single-users": "allow"}}"#;
+        assert_eq!(
+            LintConfig::from_json(json),
+            Err("unknown rule single-users".to_string())
+        );
+
+        // critical sections are compared in the common time base
+        let mut tasks = Tasks::load(&PathBuf::from("task_sets/task_set2.json")).unwrap();
+        tasks.0[0].time_base.unit = TimeUnit::Us;
+        tasks.0[1].time_base.unit = TimeUnit::Us;
+        tasks.0[2].time_base.unit = TimeUnit::Ms;
+        let findings = tasks.lint(&LintConfig::default()).unwrap();
+        assert!(!rules(&findings).contains(&("long-critical-section", Some("R3"))));
+    }
+}
@@ -1,25 +1,666 @@
-use srp::common::Tasks;
+use srp::acceptance::{Experiment, Variant};
+use srp::chain::Chain;
+use srp::common::{Tasks, Time, TimeBase, TimeUnit};
+use srp::diff::Diff;
+use srp::generate::{GenConfig, Rng, MAX_TASKS};
+use srp::graph::{Graph, GraphFormat};
+use srp::lint::{Finding, LintConfig, Severity, RULES};
+use srp::policy::Policy;
+use srp::report::{Report, ReportFormat};
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(default_value=PathBuf::from("task_sets/task_set1.json").into_os_string())]
     path: PathBuf,
 
     #[arg(short, long, action = clap::ArgAction::Count)]
     debug: u8,
+
+    /// Time unit for displaying results (cycles, ns, us, ms, s)
+    #[arg(short, long)]
+    unit: Option<TimeUnit>,
+
+    /// Clock frequency in Hz, for tasks given in cycles without a clock frequency
+    #[arg(long)]
+    clock_hz: Option<u64>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Import a task set from an event log (`<ts> enter|exit|lock|unlock <id>`)
+    ImportLog {
+        log: PathBuf,
+
+        /// Output task set
+        #[arg(short, long)]
+        out: PathBuf,
+
+        /// Time unit of the log timestamps (cycles, ns, us, ms, s)
+        #[arg(short, long, default_value = "cycles")]
+        unit: TimeUnit,
+
+        /// Clock frequency in Hz, for timestamps given in cycles
+        #[arg(long)]
+        clock_hz: Option<u64>,
+
+        /// Period (deadline and inter-arrival) of tasks observed only once
+        #[arg(short, long)]
+        period: Option<Time>,
+    },
+    /// Import a skeleton task set (priorities and resources) from an RTIC application
+    Rtic {
+        app: PathBuf,
+
+        /// Output task set
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+
+        /// Report inconsistencies between the application and this task set
+        #[arg(short, long)]
+        check: Option<PathBuf>,
+    },
+    /// Merge measured traces into a structural task set (JSON or RTIC application)
+    Merge {
+        structure: PathBuf,
+
+        measured: PathBuf,
+
+        /// Output task set
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+    /// Export the task/resource graph (or the resource nesting graph)
+    Graph {
+        path: PathBuf,
+
+        /// Output format (dot, mermaid)
+        #[arg(short, long, default_value = "dot")]
+        format: GraphFormat,
+
+        /// Export the resource nesting graph
+        #[arg(short, long)]
+        nesting: bool,
+
+        /// Output file, printed if not given
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Report resource ceilings and usage
+    Resources { path: PathBuf },
+    /// Export the task set as a draw.io diagram
+    ExportDrawio {
+        path: PathBuf,
+
+        /// Output diagram
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+    /// Import a task set from a draw.io diagram (as exported by export-drawio)
+    ImportDrawio {
+        diagram: PathBuf,
+
+        /// Output task set
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+    /// Check the task set for design smells
+    Lint {
+        path: PathBuf,
+
+        /// Lint configuration (JSON), with rule severities and cs_fraction
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Override the severity of a rule, e.g., single-user=allow
+        #[arg(short, long, value_parser = parse_rule)]
+        rule: Vec<(String, Severity)>,
+
+        /// Fraction of the shortest blocked deadline a critical section may take
+        #[arg(long)]
+        cs_fraction: Option<f64>,
+    },
+    /// Report analysis results and lint findings as JUnit XML or SARIF
+    Report {
+        path: PathBuf,
+
+        /// Report format (junit, sarif)
+        #[arg(short, long, default_value = "junit")]
+        format: ReportFormat,
+
+        /// Minimum slack to the deadline, as a fraction of the deadline
+        #[arg(long, default_value_t = 0.0)]
+        min_slack: f64,
+
+        /// Lint configuration (JSON)
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Validate against a structural task set or RTIC application (.rs)
+        #[arg(short, long)]
+        structure: Option<PathBuf>,
+
+        /// Output report
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+    /// Check schedulability policies, exiting with the bits of the violated policies
+    /// (2 unschedulable, 4 slack, 8 utilization, 16 jitter, 32 queue capacity),
+    /// or 1 if the task set cannot be loaded or analyzed
+    Check {
+        path: PathBuf,
+
+        /// Do not require all tasks to be schedulable
+        #[arg(long)]
+        allow_unschedulable: bool,
+
+        /// Minimum slack to the deadline, as a fraction of the deadline (e.g., 0.1 for 10%)
+        #[arg(long)]
+        min_slack: Option<f64>,
+
+        /// Maximum total utilization
+        #[arg(long)]
+        max_utilization: Option<f32>,
+
+        /// Maximum response time jitter, as a fraction of the inter-arrival
+        #[arg(long)]
+        max_jitter: Option<f64>,
+
+        /// Do not require the declared queue capacities of software tasks to suffice
+        #[arg(long)]
+        allow_queue_overflow: bool,
+    },
+    /// Show the changes between two task sets and their analysis results
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+
+        /// Output JSON instead of text
+        #[arg(long)]
+        json: bool,
+
+        /// Output file, printed if not given
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Compute the end-to-end latency of chains of spawned tasks
+    Latency {
+        path: PathBuf,
+
+        /// Chain of tasks, each spawning the next, e.g., T1,T2,T3
+        #[arg(short, long = "chain", required = true)]
+        chains: Vec<Chain>,
+
+        /// Output file, printed if not given
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Generate a random task set
+    Generate {
+        /// Seed, the same seed gives the same task set
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+
+        /// Total utilization
+        #[arg(short, long, default_value_t = 0.5)]
+        utilization: f64,
+
+        #[command(flatten)]
+        generate: GenArgs,
+
+        /// Output task set
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+    /// Run an acceptance ratio experiment over random task sets
+    Acceptance {
+        /// Seed of the first task set
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+
+        /// Task sets per utilization
+        #[arg(short, long, default_value_t = 100)]
+        sets: usize,
+
+        /// Lowest utilization
+        #[arg(long, default_value_t = 0.1)]
+        from: f64,
+
+        /// Highest utilization
+        #[arg(long, default_value_t = 1.0)]
+        to: f64,
+
+        /// Utilization step
+        #[arg(long, default_value_t = 0.1)]
+        step: f64,
+
+        /// Schedulability tests to compare (analysis, exact, simulation)
+        #[arg(
+            short,
+            long,
+            value_delimiter = ',',
+            default_value = "analysis,exact,simulation"
+        )]
+        variants: Vec<Variant>,
+
+        /// Number of threads, all cores if not given
+        #[arg(long)]
+        threads: Option<usize>,
+
+        #[command(flatten)]
+        generate: GenArgs,
+
+        /// Output CSV
+        #[arg(short, long)]
+        out: PathBuf,
+
+        /// Output SVG chart
+        #[arg(long)]
+        chart: Option<PathBuf>,
+    },
+}
+
+// Options of random task sets
+#[derive(Args, Debug)]
+struct GenArgs {
+    /// Number of tasks (1 to 255, each at a distinct priority)
+    #[arg(short, long, default_value_t = 5, value_parser = parse_tasks)]
+    tasks: usize,
+
+    /// Shortest period
+    #[arg(long, default_value_t = 100)]
+    period_min: u64,
+
+    /// Longest period
+    #[arg(long, default_value_t = 10_000)]
+    period_max: u64,
+
+    /// Number of resources
+    #[arg(short, long, default_value_t = 3)]
+    resources: usize,
+
+    /// Maximum number of critical sections per task (outermost)
+    #[arg(long, default_value_t = 2)]
+    max_sections: usize,
+
+    /// Maximum nesting depth of critical sections
+    #[arg(long, default_value_t = 2)]
+    max_nesting: usize,
+
+    /// Shortest critical section, as a fraction of the enclosing section
+    #[arg(long, default_value_t = 0.05)]
+    cs_min: f64,
+
+    /// Longest critical section, as a fraction of the enclosing section
+    #[arg(long, default_value_t = 0.3)]
+    cs_max: f64,
+}
+
+impl GenArgs {
+    fn config(&self, utilization: f64) -> GenConfig {
+        GenConfig {
+            tasks: self.tasks,
+            utilization,
+            period_min: self.period_min,
+            period_max: self.period_max,
+            resources: self.resources,
+            max_sections: self.max_sections,
+            max_nesting: self.max_nesting,
+            cs_min: self.cs_min,
+            cs_max: self.cs_max,
+        }
+    }
+}
+
+fn parse_rule(s: &str) -> Result<(String, Severity), String> {
+    let (name, severity) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected <rule>=<severity>, got {}", s))?;
+    if !RULES.iter().any(|r| r.name == name) {
+        return Err(format!("unknown rule {}", name));
+    }
+    Ok((name.to_string(), severity.parse()?))
+}
+
+fn parse_tasks(s: &str) -> Result<usize, String> {
+    let tasks: usize = s.parse().map_err(|e| format!("{}", e))?;
+    if !(1..=MAX_TASKS).contains(&tasks) {
+        return Err(format!("expected 1 to {} tasks, got {}", MAX_TASKS, tasks));
+    }
+    Ok(tasks)
 }
 
 fn main() {
     let cli = Cli::parse();
 
-    let tasks = Tasks::load(&cli.path).unwrap();
+    match cli.command {
+        Some(Command::ImportLog {
+            log,
+            out,
+            unit,
+            clock_hz,
+            period,
+        }) => {
+            let log = std::fs::read_to_string(log).unwrap();
+            let tasks = srp::event_log::import(&log, TimeBase { unit, clock_hz }, period).unwrap();
+            println!("Task set\n{}", tasks);
+            tasks.store(&out).unwrap();
+        }
+        Some(Command::Rtic { app, out, check }) => {
+            let src = std::fs::read_to_string(app).unwrap();
+            let app = srp::rtic::App::parse(&src).unwrap();
+            let tasks = app.tasks();
+            println!("Task set\n{}", tasks);
+            if let Some(out) = out {
+                tasks.store(&out).unwrap();
+            }
+            if let Some(check) = check {
+                let inconsistencies = app.check(&Tasks::load(&check).unwrap());
+                for i in &inconsistencies {
+                    println!("{}", i);
+                }
+                if !inconsistencies.is_empty() {
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Command::Merge {
+            structure,
+            measured,
+            out,
+        }) => {
+            let structure = if structure.extension().is_some_and(|e| e == "rs") {
+                let src = std::fs::read_to_string(structure).unwrap();
+                srp::rtic::App::parse(&src).unwrap().tasks()
+            } else {
+                Tasks::load(&structure).unwrap()
+            };
+            let measured = Tasks::load(&measured).unwrap();
+            let (tasks, inconsistencies) = match srp::merge::merge(&structure, &measured) {
+                Ok(merged) => merged,
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            for i in &inconsistencies {
+                println!("{}", i);
+            }
+            println!("Task set\n{}", tasks);
+            tasks.store(&out).unwrap();
+        }
+        Some(Command::Graph {
+            path,
+            format,
+            nesting,
+            out,
+        }) => {
+            let tasks = Tasks::load(&path).unwrap();
+            let graph = if nesting {
+                Graph::nesting(&tasks)
+            } else {
+                Graph::resources(&tasks)
+            };
+            let graph = graph.render(format);
+            match out {
+                Some(out) => std::fs::write(out, graph).unwrap(),
+                None => print!("{}", graph),
+            }
+        }
+        Some(Command::Resources { path }) => {
+            let tasks = Tasks::load(&path).unwrap();
+            println!("{}", tasks.resource_table());
+        }
+        Some(Command::ExportDrawio { path, out }) => {
+            let tasks = Tasks::load(&path).unwrap();
+            std::fs::write(out, srp::drawio::export(&tasks)).unwrap();
+        }
+        Some(Command::ImportDrawio { diagram, out }) => {
+            let xml = std::fs::read_to_string(diagram).unwrap();
+            let tasks = match srp::drawio::import(&xml) {
+                Ok(tasks) => tasks,
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            println!("Task set\n{}", tasks);
+            tasks.store(&out).unwrap();
+        }
+        Some(Command::Lint {
+            path,
+            config,
+            rule,
+            cs_fraction,
+        }) => {
+            let tasks = Tasks::load(&path).unwrap();
+            let mut config: LintConfig = match config {
+                Some(config) => {
+                    match LintConfig::from_json(&std::fs::read_to_string(config).unwrap()) {
+                        Ok(config) => config,
+                        Err(e) => {
+                            eprintln!("error: {}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                None => LintConfig::default(),
+            };
+            config.rules.extend(rule);
+            if let Some(cs_fraction) = cs_fraction {
+                config.cs_fraction = cs_fraction;
+            }
+            let findings = match tasks.lint(&config) {
+                Ok(findings) => findings,
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            for f in &findings {
+                println!("{}", f);
+            }
+            if findings.iter().any(|f| f.severity == Severity::Error) {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Report {
+            path,
+            format,
+            min_slack,
+            config,
+            structure,
+            out,
+        }) => {
+            let tasks = Tasks::load(&path).unwrap();
+            let config: LintConfig = match config {
+                Some(config) => {
+                    match LintConfig::from_json(&std::fs::read_to_string(config).unwrap()) {
+                        Ok(config) => config,
+                        Err(e) => {
+                            eprintln!("error: {}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                None => LintConfig::default(),
+            };
+            let mut findings = tasks.lint(&config).unwrap();
+            if let Some(structure) = structure {
+                let inconsistencies = if structure.extension().is_some_and(|e| e == "rs") {
+                    let src = std::fs::read_to_string(structure).unwrap();
+                    srp::rtic::App::parse(&src).unwrap().check(&tasks)
+                } else {
+                    srp::merge::check(&Tasks::load(&structure).unwrap(), &tasks)
+                };
+                findings.extend(inconsistencies.iter().map(Finding::from));
+            }
+            let results = tasks.response_time().unwrap();
+            let report = Report {
+                source: path.display().to_string(),
+                results: &results,
+                findings,
+                min_slack,
+            };
+            std::fs::write(out, report.render(format)).unwrap();
+        }
+        Some(Command::Check {
+            path,
+            allow_unschedulable,
+            min_slack,
+            max_utilization,
+            max_jitter,
+            allow_queue_overflow,
+        }) => {
+            let fail = |e: &dyn std::fmt::Display| -> ! {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            };
+            let tasks = Tasks::load(&path).unwrap_or_else(|e| fail(&e));
+            let policy = Policy {
+                schedulable: !allow_unschedulable,
+                min_slack,
+                max_utilization,
+                max_jitter,
+                capacity: !allow_queue_overflow,
+            };
+            let results = tasks.response_time().unwrap_or_else(|e| fail(&e));
+            let violations = policy.check(&results, tasks.total_utilization());
+            for v in &violations {
+                println!("{}", v);
+            }
+            std::process::exit(srp::policy::exit_code(&violations));
+        }
+        Some(Command::Diff {
+            old,
+            new,
+            json,
+            out,
+        }) => {
+            let old = Tasks::load(&old).unwrap().response_time().unwrap();
+            let new = Tasks::load(&new).unwrap().response_time().unwrap();
+            let diff = match Diff::results(&old, &new) {
+                Ok(diff) => diff,
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let diff = if json {
+                serde_json::to_string_pretty(&diff).unwrap()
+            } else {
+                diff.to_string()
+            };
+            match out {
+                Some(out) => std::fs::write(out, diff).unwrap(),
+                None => print!("Diff\n{}", diff),
+            }
+        }
+        Some(Command::Latency { path, chains, out }) => {
+            let results = Tasks::load(&path).unwrap().response_time().unwrap();
+            let latencies = match chains
+                .iter()
+                .map(|c| c.latency(&results).map(|l| l.to_string()))
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(latencies) => latencies.join("\n"),
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            match out {
+                Some(out) => std::fs::write(out, latencies).unwrap(),
+                None => print!("Latency\n{}", latencies),
+            }
+        }
+        Some(Command::Generate {
+            seed,
+            utilization,
+            generate,
+            out,
+        }) => {
+            let tasks = match generate.config(utilization).generate(&mut Rng::new(seed)) {
+                Ok(tasks) => tasks,
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            println!("Task set\n{}", tasks);
+            tasks.store(&out).unwrap();
+        }
+        Some(Command::Acceptance {
+            seed,
+            sets,
+            from,
+            to,
+            step,
+            variants,
+            threads,
+            generate,
+            out,
+            chart,
+        }) => {
+            if step <= 0.0 {
+                eprintln!("error: --step must be positive");
+                std::process::exit(1);
+            }
+            let steps = ((to - from) / step).round().max(0.0) as usize;
+            let experiment = Experiment {
+                generate: generate.config(from),
+                utilizations: (0..=steps).map(|i| from + i as f64 * step).collect(),
+                sets,
+                seed,
+                threads: threads
+                    .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get())),
+                variants,
+            };
+            let rows = match experiment.run() {
+                Ok(rows) => rows,
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let csv = srp::acceptance::csv(&experiment.variants, &rows);
+            print!("Acceptance ratio\n{}", csv);
+            std::fs::write(out, csv).unwrap();
+            if let Some(chart) = chart {
+                srp::acceptance::chart(&chart, &experiment.variants, &rows).unwrap();
+            }
+        }
+        None => analyze(&cli),
+    }
+}
+
+fn analyze(cli: &Cli) {
+    let mut tasks = Tasks::load(&cli.path).unwrap();
+    if let Some(clock_hz) = cli.clock_hz {
+        for t in tasks.0.iter_mut() {
+            if t.time_base.unit == TimeUnit::Cycles && t.time_base.clock_hz.is_none() {
+                t.time_base.clock_hz = Some(clock_hz);
+            }
+        }
+    }
     println!("Task set\n{}", tasks);
 
     println!("tot_util {}", tasks.total_utilization());
     println!("response time");
-    tasks.response_time();
+    let results = tasks.response_time().unwrap();
+    let results = match cli.unit {
+        Some(unit) => {
+            let clock_hz = tasks.clock_hz().unwrap();
+            results.convert(&TimeBase { unit, clock_hz }).unwrap()
+        }
+        None => results,
+    };
+    println!("{}", results);
 }
@@ -0,0 +1,188 @@
+// Merge of a structural task set with measured timings
+//
+// The structure (task ids, priorities and the resources each task may lock)
+// comes from one source, e.g., an RTIC application, and the traces from
+// another, e.g., an event log.
+
+use crate::common::*;
+use std::fmt;
+
+// A disagreement between the structure and the measured task set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inconsistency {
+    // Task declared in the structure but missing in the measurements
+    MissingTask(String),
+    // Measured task not declared in the structure
+    UnknownTask(String),
+    // Task priorities differ (task, declared, measured)
+    Priority(String, u8, u8),
+    // Resource locked in the measurements but not declared for the task
+    UndeclaredResource(String, String),
+    // Resource declared for the task but never locked in the measurements
+    UnusedResource(String, String),
+    // Resource used by a task but not declared in the #[shared] struct
+    UnknownResource(String, String),
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inconsistency::MissingTask(t) => write!(f, "task {} missing in task set", t),
+            Inconsistency::UnknownTask(t) => write!(f, "task {} not declared", t),
+            Inconsistency::Priority(t, declared, set) => write!(
+                f,
+                "task {} declared at priority {}, task set has {}",
+                t, declared, set
+            ),
+            Inconsistency::UndeclaredResource(t, r) => {
+                write!(f, "task {} locks undeclared resource {}", t, r)
+            }
+            Inconsistency::UnusedResource(t, r) => {
+                write!(f, "task {} never locks declared resource {}", t, r)
+            }
+            Inconsistency::UnknownResource(t, r) => {
+                write!(f, "task {} uses resource {} not in #[shared]", t, r)
+            }
+        }
+    }
+}
+
+// Inconsistencies between the structure and the measured task set.
+// Measured tasks at priority 0 (unknown priority) are not checked for priority.
+pub fn check(structure: &Tasks, measured: &Tasks) -> Vec<Inconsistency> {
+    let mut found = vec![];
+    for s in &structure.0 {
+        let Some(m) = measured.0.iter().find(|m| m.id == s.id) else {
+            found.push(Inconsistency::MissingTask(s.id.clone()));
+            continue;
+        };
+        if m.prio != 0 && m.prio != s.prio {
+            found.push(Inconsistency::Priority(s.id.clone(), s.prio, m.prio));
+        }
+        let declared = s.critical_sections();
+        let locked = m.critical_sections();
+        let mut ids: Vec<&String> = locked.keys().collect();
+        ids.sort();
+        for r in ids {
+            if !declared.contains_key(r) {
+                found.push(Inconsistency::UndeclaredResource(s.id.clone(), r.clone()));
+            }
+        }
+        let mut ids: Vec<&String> = declared.keys().collect();
+        ids.sort();
+        for r in ids {
+            if !locked.contains_key(r) {
+                found.push(Inconsistency::UnusedResource(s.id.clone(), r.clone()));
+            }
+        }
+    }
+    for m in &measured.0 {
+        if !structure.0.iter().any(|s| s.id == m.id) {
+            found.push(Inconsistency::UnknownTask(m.id.clone()));
+        }
+    }
+    found
+}
+
+// Merge the measured traces into the structure.
+// Priorities are taken from the structure, deadline and inter-arrival from the
+// structure unless left at 0. Timing values of the structure are converted into
+// the time base of the measurements. Tasks missing in the measurements are kept
+// as is, measured tasks not in the structure are dropped (and reported).
+pub fn merge(
+    structure: &Tasks,
+    measured: &Tasks,
+) -> Result<(Tasks, Vec<Inconsistency>), AnalysisError> {
+    let merged = structure
+        .0
+        .iter()
+        .map(|s| match measured.0.iter().find(|m| m.id == s.id) {
+            Some(m) => {
+                let convert = |v: Time| s.time_base.convert(v, &m.time_base, &s.id);
+                Ok(Task {
+                    id: s.id.clone(),
+                    prio: s.prio,
+                    deadline: if s.deadline != 0 {
+                        convert(s.deadline)?
+                    } else {
+                        m.deadline
+                    },
+                    inter_arrival: if s.inter_arrival != 0 {
+                        convert(s.inter_arrival)?
+                    } else {
+                        m.inter_arrival
+                    },
+                    trace: m.trace.clone(),
+                    traces: m.traces.clone(),
+                    time_base: m.time_base,
+                    spawns: s.spawns.clone(),
+                    periodic: s.periodic,
+                    dispatch: s
+                        .dispatch
+                        .as_ref()
+                        .map(|d| {
+                            Ok::<_, AnalysisError>(Dispatch {
+                                overhead: convert(d.overhead)?,
+                                ..d.clone()
+                            })
+                        })
+                        .transpose()?,
+                })
+            }
+            None => Ok(s.clone()),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((Tasks(merged), check(structure, measured)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merge_log() {
+        let structure = crate::task_sets::task_set1();
+        let log = "
+            0 enter T2
+            1 lock R1
+            3 lock R3
+            4 unlock R3
+            5 unlock R1
+            8 exit T2
+            10 enter T4
+            11 exit T4
+        ";
+        let measured = crate::event_log::import(log, TimeBase::default(), Some(1000)).unwrap();
+        let (merged, found) = merge(&structure, &measured).unwrap();
+
+        let t2 = &merged.0[1];
+        assert_eq!(t2.prio, 2);
+        assert_eq!(t2.deadline, 200);
+        assert_eq!(t2.wcet(), 8);
+        assert_eq!(merged.0[0], structure.0[0]);
+        assert_eq!(
+            found,
+            vec![
+                Inconsistency::MissingTask("T1".to_string()),
+                Inconsistency::UndeclaredResource("T2".to_string(), "R3".to_string()),
+                Inconsistency::UnusedResource("T2".to_string(), "R2".to_string()),
+                Inconsistency::MissingTask("T3".to_string()),
+                Inconsistency::UnknownTask("T4".to_string()),
+            ]
+        );
+
+        // deadlines in ms, measurements in cycles
+        let mut structure = structure;
+        structure.0[1].time_base.unit = TimeUnit::Ms;
+        let mut measured = measured;
+        measured.0[0].time_base.clock_hz = Some(16_000_000);
+        let (merged, _) = merge(&structure, &measured).unwrap();
+        assert_eq!(merged.0[1].deadline, 200 * 16_000);
+        assert_eq!(merged.0[1].time_base, measured.0[0].time_base);
+        measured.0[0].time_base.clock_hz = None;
+        assert_eq!(
+            merge(&structure, &measured),
+            Err(AnalysisError::MissingClock("T2".to_string()))
+        );
+    }
+}
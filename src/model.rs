@@ -0,0 +1,619 @@
+// Indexed model of a task set, precomputed for analysis
+//
+// Tasks are referred to by index, with indices sorted by decreasing priority
+// so that the tasks of higher (and equal) priority than a task form a range.
+// The worst blocking is computed once per priority level, so the analysis of a
+// task is plain arithmetic over its ranges. The model keeps only the timing
+// parameters of the tasks, so results take ownership of their tasks.
+
+use crate::common::*;
+use std::collections::HashMap;
+use std::ops::Range;
+
+// The default bound on the iterations of a busy period recurrence
+pub const MAX_ITERATIONS: usize = 10_000;
+
+pub struct Model {
+    pub ids: Vec<String>,
+    pub prio: Vec<u8>,
+    pub deadline: Vec<Time>,
+    pub inter_arrival: Vec<Time>,
+    pub wcet: Vec<Time>,
+    pub bcet: Vec<Time>,
+    pub periodic: Vec<bool>,
+    // task indices by decreasing priority, in task order within a priority
+    pub order: Vec<usize>,
+    // per task, the range of order with higher priority, and with equal priority
+    pub higher: Vec<Range<usize>>,
+    pub equal: Vec<Range<usize>>,
+    // per priority level (decreasing), the worst blocker by lower priority tasks
+    pub blocking: Vec<(u8, Option<Blocker>)>,
+    // iterations of a busy period recurrence before giving up
+    pub max_iterations: usize,
+    // per task, the release jitter inherited from its spawners
+    pub jitter: Vec<Time>,
+    // per task, whether its release jitter is unbounded, as it is spawned
+    // (directly or not) by a task that is not schedulable
+    pub unbounded: Vec<bool>,
+}
+
+impl Model {
+    pub fn new(tasks: &Tasks) -> Result<Model, AnalysisError> {
+        let n = tasks.0.len();
+        let overflow = |t: &Task| AnalysisError::Overflow(t.id.clone());
+        // software tasks are charged the overhead of their dispatcher
+        let wcet: Vec<Time> = tasks
+            .0
+            .iter()
+            .map(|t| t.checked_wcet().ok_or_else(|| overflow(t)))
+            .collect::<Result<_, _>>()?;
+        let bcet: Vec<Time> = tasks
+            .0
+            .iter()
+            .map(|t| {
+                t.bcet()
+                    .checked_add(t.overhead())
+                    .ok_or_else(|| overflow(t))
+            })
+            .collect::<Result<_, _>>()?;
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by_key(|i| std::cmp::Reverse(tasks.0[*i].prio));
+
+        let range = |i: usize| {
+            let p = tasks.0[i].prio;
+            let start = order.partition_point(|j| tasks.0[*j].prio > p);
+            let end = order.partition_point(|j| tasks.0[*j].prio >= p);
+            (start, end)
+        };
+        let higher = (0..n).map(|i| 0..range(i).0).collect();
+        let equal = (0..n)
+            .map(|i| {
+                let (start, end) = range(i);
+                start..end
+            })
+            .collect();
+
+        // the critical sections of each task, from a single walk of its traces
+        let paths: Vec<HashMap<String, (Time, Vec<String>)>> =
+            tasks.0.iter().map(|t| t.critical_section_paths()).collect();
+        let mut resources: Vec<&str> = paths
+            .iter()
+            .flat_map(|cs| cs.keys().map(String::as_str))
+            .collect();
+        resources.sort();
+        resources.dedup();
+        let index = |r: &str| resources.binary_search(&r).unwrap();
+
+        // the ceiling of each resource, as in the resource table
+        let mut ceiling = vec![0; resources.len()];
+        for (t, cs) in tasks.0.iter().zip(&paths) {
+            for r in cs.keys() {
+                let c = &mut ceiling[index(r)];
+                *c = (*c).max(t.prio);
+            }
+        }
+
+        // per task, its sections as (resource index, length, path), sorted by resource
+        let cs: Vec<Vec<(usize, Time, &Vec<String>)>> = paths
+            .iter()
+            .map(|cs| {
+                let mut cs: Vec<_> = cs
+                    .iter()
+                    .map(|(r, (length, path))| (index(r), *length, path))
+                    .collect();
+                cs.sort_by_key(|(r, _, _)| *r);
+                cs
+            })
+            .collect();
+
+        let mut levels: Vec<u8> = tasks.0.iter().map(|t| t.prio).collect();
+        levels.sort_by(|a, b| b.cmp(a));
+        levels.dedup();
+        let blocking = levels
+            .into_iter()
+            .map(|p| {
+                // the first (by task, then resource) of maximal length over
+                // lower tasks, with a ceiling of at least p
+                let mut worst: Option<(usize, usize, Time, &Vec<String>)> = None;
+                for j in (0..n).filter(|j| tasks.0[*j].prio < p) {
+                    for &(r, length, path) in &cs[j] {
+                        if length > 0
+                            && ceiling[r] >= p
+                            && worst.is_none_or(|(_, _, l, _)| length > l)
+                        {
+                            worst = Some((j, r, length, path));
+                        }
+                    }
+                }
+                let b = worst.map(|(j, r, length, path)| Blocker {
+                    task: tasks.0[j].id.clone(),
+                    resource: resources[r].to_string(),
+                    path: path.clone(),
+                    length,
+                });
+                (p, b)
+            })
+            .collect();
+
+        Ok(Model {
+            ids: tasks.0.iter().map(|t| t.id.clone()).collect(),
+            prio: tasks.0.iter().map(|t| t.prio).collect(),
+            deadline: tasks.0.iter().map(|t| t.deadline).collect(),
+            inter_arrival: tasks.0.iter().map(|t| t.inter_arrival).collect(),
+            wcet,
+            bcet,
+            periodic: tasks.0.iter().map(|t| t.periodic).collect(),
+            order,
+            higher,
+            equal,
+            blocking,
+            max_iterations: MAX_ITERATIONS,
+            jitter: vec![0; n],
+            unbounded: vec![false; n],
+        })
+    }
+
+    // The worst blocker of task i
+    pub fn blocker(&self, i: usize) -> Option<&Blocker> {
+        let p = self.prio[i];
+        self.blocking
+            .iter()
+            .find(|(level, _)| *level == p)
+            .and_then(|(_, b)| b.as_ref())
+    }
+
+    pub fn blocking(&self, i: usize) -> Time {
+        self.blocker(i).map_or(0, |b| b.length)
+    }
+
+    // The tasks of higher priority than task i
+    pub fn higher(&self, i: usize) -> &[usize] {
+        &self.order[self.higher[i].clone()]
+    }
+
+    // The busy period of job (0 for the first) of task i in the level-i busy
+    // period, starting from busy_period, iterated to a fix-point (or deadline
+    // miss). The job completes after its own wcet and blocking, the earlier
+    // jobs of task i, and the interference. Each step is recorded in iterations.
+    pub fn busy_period(
+        &self,
+        i: usize,
+        job: usize,
+        wcet_and_block: Time,
+        busy_period: Time,
+        iterations: &mut Vec<Iteration>,
+    ) -> Result<Option<Time>, AnalysisError> {
+        let overflow = || AnalysisError::Overflow(self.ids[i].clone());
+        let own = (job as Time)
+            .checked_mul(self.wcet[i])
+            .and_then(|earlier| earlier.checked_add(wcet_and_block))
+            .ok_or_else(overflow)?;
+        let limit = self.deadline[i]
+            .checked_add(self.release(i, job)?)
+            .ok_or_else(overflow)?;
+        let mut busy_period = busy_period;
+        for _ in 0..self.max_iterations {
+            let interference =
+                self.interfering(i)
+                    .try_fold(0 as Time, |interference, (j, kind)| {
+                        let pre = self
+                            .jobs(i, j, kind, busy_period)?
+                            .checked_mul(self.wcet[j])
+                            .ok_or_else(overflow)?;
+                        interference.checked_add(pre).ok_or_else(overflow)
+                    })?;
+            let next = own.checked_add(interference).ok_or_else(overflow)?;
+            iterations.push(Iteration {
+                job,
+                busy_period,
+                next,
+            });
+            if next > limit {
+                return Ok(None);
+            } else if next <= busy_period {
+                return Ok(Some(next));
+            }
+            busy_period = next;
+        }
+        Err(AnalysisError::IterationLimit(
+            self.ids[i].clone(),
+            self.max_iterations,
+        ))
+    }
+
+    // The earliest release of job of task i, relative to the first job.
+    // Jobs are released A apart, up to the release jitter J.
+    fn release(&self, i: usize, job: usize) -> Result<Time, AnalysisError> {
+        (job as Time)
+            .checked_mul(self.inter_arrival[i])
+            .map(|release| release.saturating_sub(self.jitter[i]))
+            .ok_or_else(|| AnalysisError::Overflow(self.ids[i].clone()))
+    }
+
+    // The utilization of the tasks of priority higher or equal to task i
+    pub fn level_utilization(&self, i: usize) -> Result<f64, AnalysisError> {
+        self.order[..self.equal[i].end]
+            .iter()
+            .map(|j| Ok(self.wcet[*j] as f64 / self.inter_arrival(*j)? as f64))
+            .sum()
+    }
+
+    // The inter-arrival of task j, which must not be 0
+    fn inter_arrival(&self, j: usize) -> Result<Time, AnalysisError> {
+        match self.inter_arrival[j] {
+            0 => Err(AnalysisError::InterArrival(self.ids[j].clone())),
+            a => Ok(a),
+        }
+    }
+
+    // The job of task i with the worst response time in the level-i busy
+    // period, with its busy period and response time, None on a deadline miss. Jobs of task i are
+    // served in order (FIFO), so a job released before the previous one
+    // completes queues behind it, until the level-i busy period ends. Unless
+    // the utilization at the level is below 1, the busy period need not end.
+    // A task released with unbounded jitter, or interfered by one, has no bound,
+    // nor has idle (at priority 0), which never completes.
+    fn worst_job(
+        &self,
+        i: usize,
+        iterations: &mut Vec<Iteration>,
+    ) -> Result<Option<(usize, Time, Time)>, AnalysisError> {
+        if self.prio[i] == 0
+            || self.level_utilization(i)? >= 1.0
+            || self.unbounded[i]
+            || self.interfering(i).any(|(j, _)| self.unbounded[j])
+        {
+            return Ok(None);
+        }
+        let wcet_and_block = self.wcet_and_block(i)?;
+        let mut worst: Option<(usize, Time, Time)> = None;
+        let mut start = wcet_and_block;
+        for job in 0..self.max_iterations {
+            let Some(busy_period) = self.busy_period(i, job, wcet_and_block, start, iterations)?
+            else {
+                return Ok(None);
+            };
+            let response_time = busy_period - self.release(i, job)?;
+            if worst.is_none_or(|(_, _, r)| response_time > r) {
+                worst = Some((job, busy_period, response_time));
+            }
+            // the level-i busy period ends before the next job is released
+            if busy_period <= self.release(i, job + 1)? {
+                return Ok(worst);
+            }
+            start = busy_period;
+        }
+        Err(AnalysisError::IterationLimit(
+            self.ids[i].clone(),
+            self.max_iterations,
+        ))
+    }
+
+    // The other tasks of kind to task i, by decreasing priority
+    pub fn range(&self, i: usize, kind: InterferenceKind) -> impl Iterator<Item = usize> + '_ {
+        let range = match kind {
+            InterferenceKind::Equal => self.equal[i].clone(),
+            InterferenceKind::Higher => self.higher[i].clone(),
+        };
+        self.order[range].iter().copied().filter(move |j| *j != i)
+    }
+
+    // The tasks interfering with task i, of equal and then of higher priority
+    fn interfering(&self, i: usize) -> impl Iterator<Item = (usize, InterferenceKind)> + '_ {
+        let equal = self.range(i, InterferenceKind::Equal);
+        let higher = self.range(i, InterferenceKind::Higher);
+        equal
+            .map(|j| (j, InterferenceKind::Equal))
+            .chain(higher.map(|j| (j, InterferenceKind::Higher)))
+    }
+
+    // The jobs of task j interfering with task i during busy period. A task
+    // of higher priority preempts task i by every job released in the busy
+    // period. A task of equal priority does not preempt, but may run first by
+    // every job released before task i starts, at the latest when the busy
+    // period less the wcet of task i has passed. A task with release jitter J
+    // interferes as if the busy period was J longer.
+    fn jobs(
+        &self,
+        i: usize,
+        j: usize,
+        kind: InterferenceKind,
+        busy_period: Time,
+    ) -> Result<Time, AnalysisError> {
+        let window = match kind {
+            InterferenceKind::Equal => busy_period.saturating_sub(self.wcet[i]),
+            InterferenceKind::Higher => busy_period,
+        };
+        let released = window
+            .checked_add(self.jitter[j])
+            .ok_or_else(|| AnalysisError::Overflow(self.ids[i].clone()))?;
+        Ok(1 + released / self.inter_arrival(j)?)
+    }
+
+    // The jobs and cost by each task interfering with task i, during busy period
+    fn interferers(&self, i: usize, busy_period: Time) -> Result<Vec<Interferer>, AnalysisError> {
+        self.interfering(i)
+            .map(|(j, kind)| {
+                let preemptions = self.jobs(i, j, kind, busy_period)?;
+                Ok(Interferer {
+                    task: self.ids[j].clone(),
+                    kind,
+                    preemptions,
+                    cost: preemptions
+                        .checked_mul(self.wcet[j])
+                        .ok_or_else(|| AnalysisError::Overflow(self.ids[i].clone()))?,
+                })
+            })
+            .collect()
+    }
+
+    // The best case response time of task i, by the recurrence of Redell and
+    // Sanfridson R = C + sum ceil((R - J - A) / A) * C (at least 0) over higher
+    // priority tasks, with best case execution times and without blocking.
+    // Only periodic tasks are certain to preempt, a sporadic task may be
+    // released arbitrarily late, so without periodic tasks R = C. Any response
+    // time is at least the right-hand side of itself, so the least fix-point,
+    // iterated up from C, is a lower bound (at most limit, an upper bound).
+    pub fn best_response_time(&self, i: usize, limit: Time) -> Result<Time, AnalysisError> {
+        let overflow = || AnalysisError::Overflow(self.ids[i].clone());
+        let limit = limit.max(self.bcet[i]);
+        let mut response_time = self.bcet[i];
+        for _ in 0..self.max_iterations {
+            let interference = self
+                .higher(i)
+                .iter()
+                .filter(|j| self.periodic[**j])
+                .try_fold(0 as Time, |interference, j| {
+                    let a = self.inter_arrival[*j].max(1);
+                    let released = response_time.saturating_sub(self.jitter[*j]);
+                    let nr = released.saturating_sub(a).div_ceil(a);
+                    let pre = nr.checked_mul(self.bcet[*j]).ok_or_else(overflow)?;
+                    interference.checked_add(pre).ok_or_else(overflow)
+                })?;
+            let next = self.bcet[i]
+                .checked_add(interference)
+                .ok_or_else(overflow)?;
+            if next <= response_time || next >= limit {
+                return Ok(next.min(limit));
+            }
+            response_time = next;
+        }
+        Err(AnalysisError::IterationLimit(
+            self.ids[i].clone(),
+            self.max_iterations,
+        ))
+    }
+
+    // The queue capacity needed by task i with response time r, i.e., the
+    // number of its messages that may be pending at once. A message is pending
+    // at most until its job completes, and messages are released at least A apart
+    // (up to jitter J), so at most ceil((r + J) / A) overlap.
+    pub fn required_capacity(&self, i: usize, r: Time) -> usize {
+        let pending = r
+            .saturating_add(self.jitter[i])
+            .div_ceil(self.inter_arrival[i].max(1));
+        pending.max(1) as usize
+    }
+
+    pub fn wcet_and_block(&self, i: usize) -> Result<Time, AnalysisError> {
+        self.wcet[i]
+            .checked_add(self.blocking(i))
+            .ok_or_else(|| AnalysisError::Overflow(self.ids[i].clone()))
+    }
+
+    // The worst and best case response times of task i, as needed to derive
+    // the jitter inherited by spawned tasks
+    pub fn bounds(&self, i: usize) -> Result<(Option<Time>, Time), AnalysisError> {
+        let response_time = self
+            .worst_job(i, &mut vec![])?
+            .map(|(_, _, response_time)| response_time);
+        let best = self.best_response_time(i, response_time.unwrap_or(self.deadline[i]))?;
+        Ok((response_time, best))
+    }
+
+    // The analysis of task i, taking ownership of the task for the result
+    pub fn response_time(&self, i: usize, task: Task) -> Result<TaskResult, AnalysisError> {
+        let wcet = self.wcet[i];
+        let blocker = self.blocker(i).cloned();
+        let blocking = self.blocking(i);
+        let wcet_and_block = self.wcet_and_block(i)?;
+
+        let mut iterations = vec![];
+        let mut response_time = None;
+        let mut interference = None;
+        let mut interferers = vec![];
+        if let Some((job, busy_period, r)) = self.worst_job(i, &mut iterations)? {
+            response_time = Some(r);
+            // the recurrence is monotone, so at the fix-point the costs of the
+            // interferers, including the earlier jobs of task i, add up to the
+            // interference
+            interference = Some(busy_period - wcet_and_block);
+            if job > 0 {
+                interferers.push(Interferer {
+                    task: self.ids[i].clone(),
+                    kind: InterferenceKind::Equal,
+                    preemptions: job as Time,
+                    cost: job as Time * wcet,
+                });
+            }
+            interferers.extend(self.interferers(i, busy_period)?);
+        }
+
+        let best_response_time =
+            self.best_response_time(i, response_time.unwrap_or(self.deadline[i]))?;
+        let required_capacity = task
+            .dispatch
+            .as_ref()
+            .and(response_time)
+            .map(|r| self.required_capacity(i, r));
+        Ok(TaskResult {
+            task,
+            response_time,
+            wcet,
+            blocking,
+            blocker,
+            interference,
+            interferers,
+            release_jitter: self.jitter[i],
+            required_capacity,
+            bcet: self.bcet[i],
+            best_response_time,
+            jitter: response_time.map(|r| r - best_response_time),
+            iterations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Model;
+    use crate::common::*;
+    use std::path::PathBuf;
+
+    // The response time, blocking and interference of each task, as
+    // computed by the original (cloning) implementation
+    #[test]
+    fn model_task_sets() {
+        let expected = [
+            [
+                (None, 0, None),
+                (Some(90), 0, Some(60)),
+                (Some(34), 4, Some(0)),
+            ],
+            [
+                (Some(70), 0, Some(50)),
+                (Some(60), 10, Some(20)),
+                (Some(20), 10, Some(0)),
+            ],
+            [
+                (Some(90), 0, Some(70)),
+                (Some(70), 0, Some(20)),
+                (Some(10), 0, Some(0)),
+            ],
+        ];
+        for (n, expected) in expected.iter().enumerate() {
+            let path = PathBuf::from(format!("task_sets/task_set{}.json", n + 1));
+            let tasks = Tasks::load(&path).unwrap();
+            let results: Vec<_> = tasks
+                .response_time()
+                .unwrap()
+                .0
+                .iter()
+                .map(|r| (r.response_time, r.blocking, r.interference))
+                .collect();
+            assert_eq!(results, expected);
+        }
+    }
+
+    #[test]
+    fn busy_period_iterations() {
+        let tasks = Tasks::load(&PathBuf::from("task_sets/task_set2.json")).unwrap();
+        let model = Model::new(&tasks).unwrap();
+        let step = |busy_period, next| Iteration {
+            job: 0,
+            busy_period,
+            next,
+        };
+        assert_eq!(
+            model
+                .response_time(1, tasks.0[1].clone())
+                .unwrap()
+                .iterations,
+            vec![step(40, 50), step(50, 60), step(60, 60)]
+        );
+
+        let mut model = Model::new(&tasks).unwrap();
+        model.max_iterations = 2;
+        assert_eq!(
+            model.response_time(1, tasks.0[1].clone()),
+            Err(AnalysisError::IterationLimit("T2".to_string(), 2))
+        );
+    }
+
+    #[test]
+    fn equal_priority() {
+        let task = |id: &str, prio, wcet, inter_arrival| Task {
+            id: id.to_string(),
+            prio,
+            deadline: 100,
+            inter_arrival,
+            trace: Trace {
+                id: id.to_string(),
+                end: wcet,
+                ..Trace::default()
+            },
+            ..Task::default()
+        };
+        // T1 may wait for a job of T2 of equal priority
+        let tasks = Tasks(vec![task("T1", 1, 10, 100), task("T2", 1, 50, 100)]);
+        let results = tasks.response_time().unwrap();
+        assert_eq!(results.0[0].response_time, Some(60));
+        assert_eq!(results.0[1].response_time, Some(60));
+
+        // T2 runs first by every job released before T1 starts, at 70 after
+        // the preemption by T3 and 5 jobs of T2 (released 0, 15, .., 60)
+        let tasks = Tasks(vec![
+            task("T1", 1, 10, 100),
+            task("T2", 1, 10, 15),
+            task("T3", 2, 20, 100),
+        ]);
+        let results = tasks.response_time().unwrap();
+        assert_eq!(results.0[0].response_time, Some(80));
+
+        // idle (at priority 0, never released again) blocks T1 by its critical
+        // section on R, but has no bound itself
+        let mut idle = task("idle", 0, 30, 0);
+        idle.trace.inner = vec![Trace {
+            id: "R".to_string(),
+            start: 5,
+            end: 25,
+            ..Trace::default()
+        }];
+        let mut t1 = task("T1", 1, 10, 100);
+        t1.trace.inner = idle.trace.inner.clone();
+        t1.trace.inner[0].end = 10;
+        let results = Tasks(vec![t1, idle]).response_time().unwrap();
+        assert_eq!(results.0[0].blocking, 20);
+        assert_eq!(results.0[0].response_time, Some(30));
+        assert_eq!(results.0[1].response_time, None);
+    }
+
+    #[test]
+    fn multiple_jobs() {
+        let task = |id: &str, wcet, inter_arrival| Task {
+            id: id.to_string(),
+            prio: 1,
+            deadline: 100,
+            inter_arrival,
+            trace: Trace {
+                id: id.to_string(),
+                end: wcet,
+                ..Trace::default()
+            },
+            dispatch: Some(Dispatch {
+                dispatcher: "EXTI1".to_string(),
+                capacity: 1,
+                overhead: 0,
+            }),
+            ..Task::default()
+        };
+        let tasks = Tasks(vec![task("A", 10, 15), task("B", 20, 100)]);
+        let mut model = Model::new(&tasks).unwrap();
+        // the second message of A is posted 5 after the first (A - J), and
+        // queues behind it and the message of B
+        model.jitter = vec![10, 0];
+        let a = model.response_time(0, tasks.0[0].clone()).unwrap();
+        assert_eq!(a.response_time, Some(40 - 5));
+        assert_eq!(a.interferers[0].task, "A");
+        let cost: Time = a.interferers.iter().map(|i| i.cost).sum();
+        assert_eq!(a.interference, Some(cost));
+        assert_eq!(a.required_capacity, Some(3));
+
+        // A alone exceeds the capacity of the dispatcher, its queue grows
+        // without bound
+        let tasks = Tasks(vec![task("A", 10, 6), task("B", 50, 100)]);
+        let results = tasks.response_time().unwrap();
+        assert_eq!(results.0[0].response_time, None);
+        assert_eq!(results.0[0].required_capacity, None);
+    }
+}
@@ -0,0 +1,241 @@
+// Schedulability policies, for gating merges on the analysis
+//
+// Each kind of violation has its own exit code bit, so the exit code of a
+// check tells which policies were violated.
+
+use crate::common::*;
+use crate::report::slack;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Policy {
+    // all tasks meet their deadlines
+    pub schedulable: bool,
+    // minimum slack of each task to its deadline, as a fraction of the deadline
+    pub min_slack: Option<f64>,
+    // maximum total utilization
+    pub max_utilization: Option<f32>,
+    // maximum response time jitter of each task, as a fraction of its inter-arrival
+    pub max_jitter: Option<f64>,
+    // the declared queue capacity of each software task suffices
+    pub capacity: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            schedulable: true,
+            min_slack: None,
+            max_utilization: None,
+            max_jitter: None,
+            capacity: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    // task
+    Unschedulable(String),
+    // task, slack, min_slack
+    Slack(String, f64, f64),
+    // utilization, max_utilization
+    Utilization(f32, f32),
+    // task, jitter, max_jitter
+    Jitter(String, f64, f64),
+    // task, required capacity, declared capacity
+    Capacity(String, usize, usize),
+}
+
+impl Violation {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Violation::Unschedulable(_) => 2,
+            Violation::Slack(..) => 4,
+            Violation::Utilization(..) => 8,
+            Violation::Jitter(..) => 16,
+            Violation::Capacity(..) => 32,
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Unschedulable(t) => write!(f, "task {} may miss its deadline", t),
+            Violation::Slack(t, slack, min) => {
+                write!(f, "task {} has slack {:.3}, below {}", t, slack, min)
+            }
+            Violation::Utilization(u, max) => {
+                write!(f, "total utilization {:.3} exceeds {}", u, max)
+            }
+            Violation::Jitter(t, jitter, max) => {
+                write!(f, "task {} has jitter {:.3}, above {}", t, jitter, max)
+            }
+            Violation::Capacity(t, required, declared) => write!(
+                f,
+                "task {} requires queue capacity {}, declared {}",
+                t, required, declared
+            ),
+        }
+    }
+}
+
+// The exit code for violations, the union of their bits (0 if none)
+pub fn exit_code(violations: &[Violation]) -> i32 {
+    violations.iter().fold(0, |code, v| code | v.exit_code())
+}
+
+impl Policy {
+    // Violations of the policy by the analysis results, with utilization the
+    // total utilization of the task set. Background tasks (idle) have no
+    // deadline, and are not subject to the policy.
+    pub fn check(&self, results: &TasksResult, utilization: f32) -> Vec<Violation> {
+        let mut found = vec![];
+        for r in results.0.iter().filter(|r| !r.task.is_background()) {
+            match slack(r) {
+                None => {
+                    if self.schedulable {
+                        found.push(Violation::Unschedulable(r.task.id.clone()));
+                    }
+                }
+                Some(slack) => {
+                    if let Some(min) = self.min_slack {
+                        if slack < min {
+                            found.push(Violation::Slack(r.task.id.clone(), slack, min));
+                        }
+                    }
+                    if let (true, Some(d), Some(required)) =
+                        (self.capacity, &r.task.dispatch, r.required_capacity)
+                    {
+                        if required > d.capacity {
+                            found.push(Violation::Capacity(
+                                r.task.id.clone(),
+                                required,
+                                d.capacity,
+                            ));
+                        }
+                    }
+                    if let (Some(max), Some(jitter)) = (self.max_jitter, r.jitter) {
+                        let jitter = jitter as f64 / r.task.inter_arrival.max(1) as f64;
+                        if jitter > max {
+                            found.push(Violation::Jitter(r.task.id.clone(), jitter, max));
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(max) = self.max_utilization {
+            if utilization > max {
+                found.push(Violation::Utilization(utilization, max));
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_set1() {
+        let tasks = crate::task_sets::task_set1();
+        let results = tasks.response_time().unwrap();
+        let utilization = tasks.total_utilization();
+
+        let violations = Policy::default().check(&results, utilization);
+        assert_eq!(violations, vec![Violation::Unschedulable("T1".to_string())]);
+        assert_eq!(exit_code(&violations), 2);
+
+        let policy = Policy {
+            schedulable: false,
+            min_slack: Some(0.5),
+            max_utilization: Some(0.5),
+            max_jitter: None,
+            capacity: true,
+        };
+        let violations = policy.check(&results, utilization);
+        assert_eq!(violations.len(), 2);
+        assert_eq!(
+            violations[0].to_string(),
+            "task T3 has slack 0.320, below 0.5"
+        );
+        assert_eq!(exit_code(&violations), 12);
+
+        let policy = Policy {
+            schedulable: false,
+            max_jitter: Some(0.05),
+            ..Policy::default()
+        };
+        let violations = policy.check(&results, utilization);
+        assert_eq!(exit_code(&violations), 16);
+    }
+
+    #[test]
+    fn check_capacity() {
+        let mut tasks = crate::task_sets::task_set1();
+        tasks.0[2].dispatch = Some(Dispatch {
+            dispatcher: "EXTI1".to_string(),
+            capacity: 1,
+            overhead: 0,
+        });
+        // T3 responds within 34 of its inter-arrival 50
+        let results = tasks.response_time().unwrap();
+        let policy = Policy {
+            schedulable: false,
+            ..Policy::default()
+        };
+        assert_eq!(policy.check(&results, 0.0), vec![]);
+
+        // the second message of T3 is posted before the first completes
+        tasks.0[2].inter_arrival = 32;
+        let results = tasks.response_time().unwrap();
+        let violations = policy.check(&results, 0.0);
+        assert_eq!(
+            violations,
+            vec![Violation::Capacity("T3".to_string(), 2, 1)]
+        );
+        assert_eq!(exit_code(&violations), 32);
+    }
+
+    #[test]
+    fn check_idle() {
+        let app = crate::rtic::App::parse(
+            "
+            #[rtic::app(device = stm32f4::stm32f411)]
+            mod app {
+                #[task(binds = EXTI0, shared = [r1])]
+                fn t1(cx: t1::Context) {}
+
+                #[idle(shared = [r1])]
+                fn idle(cx: idle::Context) -> ! {
+                    loop {}
+                }
+            }",
+        )
+        .unwrap();
+        let mut tasks = app.tasks();
+        for t in tasks.0.iter_mut() {
+            t.trace.end = 10;
+            t.trace.inner[0].end = 5;
+        }
+        tasks.0[0].deadline = 100;
+        tasks.0[0].inter_arrival = 100;
+        let results = tasks.response_time().unwrap();
+        // idle blocks t1, but has no bound itself
+        assert_eq!(results.0[0].response_time, Some(15));
+        assert_eq!(results.0[1].response_time, None);
+        let violations = Policy::default().check(&results, tasks.total_utilization());
+        assert_eq!(violations, vec![]);
+        assert_eq!(exit_code(&violations), 0);
+
+        let report = crate::report::Report {
+            source: "app.rs".to_string(),
+            results: &results,
+            findings: vec![],
+            min_slack: 0.5,
+        };
+        assert!(report.all_findings().is_empty());
+    }
+}
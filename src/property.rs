@@ -0,0 +1,234 @@
+// Property based testing of the analysis against simulation
+//
+// The analysis is safe if no simulated response time exceeds the analytic
+// bound. Random task sets, with some tasks sharing priorities, are simulated
+// at the synchronous release, at the worst blocking of each task, and with
+// random releases and execution times. A failing task set is shrunk to a
+// minimal one (fewer tasks, traces and critical sections) still failing.
+
+use crate::common::*;
+use crate::generate::{GenConfig, Rng};
+use crate::simulator::{Execution, Simulation};
+use std::fmt;
+
+// A task observed to respond later than its analytic bound
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub task: String,
+    pub bound: Time,
+    pub observed: Time,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "task {} observed response time {} exceeds bound {}",
+            self.task, self.observed, self.bound
+        )
+    }
+}
+
+// A minimal failing task set, with its violation
+#[derive(Debug)]
+pub struct Counterexample {
+    pub seed: u64,
+    pub tasks: Tasks,
+    pub violation: Violation,
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "seed {}: {}", self.seed, self.violation)?;
+        write!(f, "{}", serde_json::to_string_pretty(&self.tasks).unwrap())
+    }
+}
+
+// The first violation of the analytic bounds of tasks, simulating runs random
+// releases drawn from seed besides the synchronous and blocking releases
+pub fn violation(tasks: &Tasks, seed: u64, runs: usize) -> Option<Violation> {
+    let results = tasks.response_time().ok()?;
+    // random runs also vary between the worst and best case execution
+    let mut sim = Simulation::new(tasks);
+    sim.execution = Execution::Random;
+    let start = tasks.0.iter().map(|t| t.inter_arrival).max().unwrap_or(0);
+    let horizon = 3 * start;
+    let mut rng = Rng::new(seed);
+
+    let mut releases = vec![vec![]];
+    releases.extend((0..tasks.0.len()).map(|t| sim.blocking_offsets(t, start)));
+    for _ in 0..runs {
+        let offsets = tasks
+            .0
+            .iter()
+            .map(|t| rng.range(0, t.inter_arrival))
+            .collect();
+        releases.push(offsets);
+    }
+
+    for (run, offsets) in releases.iter().enumerate() {
+        let random = run > tasks.0.len();
+        let observed = sim.run(offsets, random.then_some(&mut rng), horizon);
+        for (o, r) in observed.iter().zip(&results.0) {
+            if let Some(bound) = r.response_time {
+                if o.max_response_time > bound {
+                    return Some(Violation {
+                        task: o.task.clone(),
+                        bound,
+                        observed: o.max_response_time,
+                    });
+                }
+            }
+        }
+    }
+    None
+}
+
+// helper functions
+
+// Randomly merge each priority level of tasks into the level below, so that
+// tasks share priorities as with coalesced hardware priorities
+fn merge_priorities(tasks: &mut Tasks, rng: &mut Rng) {
+    let mut prios: Vec<u8> = tasks.0.iter().map(|t| t.prio).collect();
+    prios.sort();
+    prios.dedup();
+    let mut level = 0;
+    let mut levels = vec![];
+    for (i, _) in prios.iter().enumerate() {
+        if i == 0 || rng.range(0, 1) == 0 {
+            level += 1;
+        }
+        levels.push(level);
+    }
+    for t in tasks.0.iter_mut() {
+        t.prio = levels[prios.binary_search(&t.prio).unwrap()];
+    }
+}
+
+// Task sets one step smaller than tasks
+fn candidates(tasks: &Tasks) -> Vec<Tasks> {
+    let mut smaller = vec![];
+    for i in 0..tasks.0.len() {
+        // without task i
+        let mut c = tasks.0.clone();
+        c.remove(i);
+        smaller.push(Tasks(c));
+
+        // without the alternative traces of task i
+        if !tasks.0[i].traces.is_empty() {
+            let mut c = tasks.0.clone();
+            c[i].traces.clear();
+            smaller.push(Tasks(c));
+        }
+
+        // without a node of the trace of task i
+        for n in 0..nodes(&tasks.0[i].trace) {
+            let mut c = tasks.0.clone();
+            remove_node(&mut c[i].trace, &mut { n });
+            smaller.push(Tasks(c));
+        }
+    }
+    smaller
+}
+
+fn nodes(trace: &Trace) -> usize {
+    trace.inner.iter().map(|t| 1 + nodes(t)).sum()
+}
+
+// Remove the n:th inner node (in pre-order), lifting its inner nodes
+fn remove_node(trace: &mut Trace, n: &mut usize) -> bool {
+    for i in 0..trace.inner.len() {
+        if *n == 0 {
+            let removed = trace.inner.remove(i);
+            for (j, inner) in removed.inner.into_iter().enumerate() {
+                trace.inner.insert(i + j, inner);
+            }
+            return true;
+        }
+        *n -= 1;
+        if remove_node(&mut trace.inner[i], n) {
+            return true;
+        }
+    }
+    false
+}
+
+// Shrink a task set for which fails holds, to a task set where fails holds
+// but for none of its one step smaller candidates
+pub fn shrink(tasks: Tasks, fails: impl Fn(&Tasks) -> bool) -> Tasks {
+    let mut tasks = tasks;
+    'shrink: loop {
+        for c in candidates(&tasks) {
+            if fails(&c) {
+                tasks = c;
+                continue 'shrink;
+            }
+        }
+        return tasks;
+    }
+}
+
+// Check random task sets seed..seed + sets against simulation, returning the
+// first failing set shrunk to a counterexample (panics on an invalid config)
+pub fn check(
+    config: &GenConfig,
+    seed: u64,
+    sets: usize,
+    runs: usize,
+) -> Result<(), Counterexample> {
+    for seed in seed..seed + sets as u64 {
+        let mut rng = Rng::new(seed);
+        let mut tasks = config.generate(&mut rng).expect("invalid config");
+        merge_priorities(&mut tasks, &mut rng);
+        if violation(&tasks, seed, runs).is_some() {
+            let tasks = shrink(tasks, |t| violation(t, seed, runs).is_some());
+            let violation = violation(&tasks, seed, runs).unwrap();
+            return Err(Counterexample {
+                seed,
+                tasks,
+                violation,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn analysis_bounds_simulation() {
+        for utilization in [0.3, 0.6, 0.9] {
+            let config = GenConfig {
+                tasks: 4,
+                utilization,
+                ..GenConfig::default()
+            };
+            if let Err(c) = check(&config, 0, 20, 5) {
+                panic!("{}", c);
+            }
+        }
+
+        // some generated sets share priority levels
+        let mut rng = Rng::new(0);
+        let mut tasks = GenConfig::default().generate(&mut rng).unwrap();
+        merge_priorities(&mut tasks, &mut rng);
+        let mut prios: Vec<u8> = tasks.0.iter().map(|t| t.prio).collect();
+        prios.sort();
+        prios.dedup();
+        assert!(prios.len() < tasks.0.len());
+    }
+
+    #[test]
+    fn shrink_to_minimal() {
+        let tasks = crate::task_sets::task_set1();
+        // fails as long as some task locks R2
+        let locks_r2 = |t: &Tasks| t.0.iter().any(|t| t.critical_sections().contains_key("R2"));
+        let minimal = shrink(tasks, locks_r2);
+        assert_eq!(minimal.0.len(), 1);
+        assert_eq!(minimal.0[0].trace.inner.len(), 1);
+        assert_eq!(minimal.0[0].trace.inner[0].id, "R2");
+        assert!(minimal.0[0].trace.inner[0].inner.is_empty());
+    }
+}
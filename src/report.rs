@@ -0,0 +1,339 @@
+// Reports for CI, JUnit XML (test dashboards) and SARIF (code scanning)
+//
+// Each analyzed task is a test case, failing if the task is not schedulable
+// or its slack to the deadline is below min_slack. Lint and validation
+// findings are reported alongside.
+
+use crate::common::*;
+use crate::escape::escape;
+use crate::lint::{Finding, Severity, RULES};
+use crate::merge::Inconsistency;
+use serde_json::json;
+use std::fmt::Write;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Junit,
+    Sarif,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "junit" => Ok(ReportFormat::Junit),
+            "sarif" => Ok(ReportFormat::Sarif),
+            _ => Err(format!("unknown report format {}", s)),
+        }
+    }
+}
+
+pub struct Report<'a> {
+    // the analyzed task set, used as artifact location in SARIF
+    pub source: String,
+    pub results: &'a TasksResult,
+    // findings of lint and validation
+    pub findings: Vec<Finding>,
+    // minimum slack to the deadline, as a fraction of the deadline
+    pub min_slack: f64,
+}
+
+// Rules reported for the analysis itself
+const ANALYSIS_RULES: &[(&str, &str)] = &[
+    ("unschedulable", "a task that may miss its deadline"),
+    (
+        "insufficient-slack",
+        "a task with less than the required slack to its deadline",
+    ),
+];
+
+// Rules reported for validation against the declared structure
+const VALIDATION_RULES: &[(&str, &str)] = &[
+    ("missing-task", "a declared task missing in the task set"),
+    (
+        "unknown-task",
+        "a task in the task set that is not declared",
+    ),
+    (
+        "priority",
+        "a task with a priority differing from its declaration",
+    ),
+    (
+        "undeclared-resource",
+        "a task locking an undeclared resource",
+    ),
+    (
+        "unused-resource",
+        "a task never locking a declared resource",
+    ),
+    (
+        "unknown-resource",
+        "a task using a resource not in #[shared]",
+    ),
+];
+
+impl From<&Inconsistency> for Finding {
+    fn from(i: &Inconsistency) -> Finding {
+        let (rule, task, resource) = match i {
+            Inconsistency::MissingTask(t) => ("missing-task", t, None),
+            Inconsistency::UnknownTask(t) => ("unknown-task", t, None),
+            Inconsistency::Priority(t, _, _) => ("priority", t, None),
+            Inconsistency::UndeclaredResource(t, r) => ("undeclared-resource", t, Some(r)),
+            Inconsistency::UnusedResource(t, r) => ("unused-resource", t, Some(r)),
+            Inconsistency::UnknownResource(t, r) => ("unknown-resource", t, Some(r)),
+        };
+        Finding {
+            rule: rule.to_string(),
+            severity: Severity::Error,
+            task: Some(task.clone()),
+            resource: resource.cloned(),
+            message: i.to_string(),
+        }
+    }
+}
+
+// Slack to the deadline as a fraction of the deadline
+pub fn slack(r: &TaskResult) -> Option<f64> {
+    let response_time = r.response_time?;
+    if r.task.deadline == 0 {
+        return Some(0.0);
+    }
+    Some(r.task.deadline.saturating_sub(response_time) as f64 / r.task.deadline as f64)
+}
+
+fn xml_text(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;")
+}
+
+impl Report<'_> {
+    // The failure (rule, message) of a task result, if any, none for a
+    // background task (idle) without deadline
+    pub fn failure(&self, r: &TaskResult) -> Option<(&'static str, String)> {
+        if r.task.is_background() {
+            return None;
+        }
+        match slack(r) {
+            None => Some((
+                "unschedulable",
+                format!(
+                    "task {} may miss its deadline {}",
+                    r.task.id, r.task.deadline
+                ),
+            )),
+            Some(slack) if slack < self.min_slack => Some((
+                "insufficient-slack",
+                format!(
+                    "task {} has slack {:.3} to its deadline, below {}",
+                    r.task.id, slack, self.min_slack
+                ),
+            )),
+            _ => None,
+        }
+    }
+
+    // Failing tasks and findings as one list of findings
+    pub fn all_findings(&self) -> Vec<Finding> {
+        let mut findings: Vec<Finding> = self
+            .results
+            .0
+            .iter()
+            .filter_map(|r| {
+                let (rule, message) = self.failure(r)?;
+                Some(Finding {
+                    rule: rule.to_string(),
+                    severity: Severity::Error,
+                    task: Some(r.task.id.clone()),
+                    resource: r.blocker.as_ref().map(|b| b.resource.clone()),
+                    message: format!("{}\n{}", message, r),
+                })
+            })
+            .collect();
+        findings.extend(self.findings.iter().cloned());
+        findings
+    }
+
+    // JUnit XML, a test suite of the analyzed tasks and one of the findings.
+    // Findings of severity info are reported as passed test cases.
+    pub fn junit(&self) -> String {
+        let mut tests = String::new();
+        let mut failures = 0;
+        for r in &self.results.0 {
+            writeln!(
+                tests,
+                "    <testcase name=\"{}\" classname=\"srp.schedulability\">",
+                escape(&r.task.id)
+            )
+            .unwrap();
+            if let Some((rule, message)) = self.failure(r) {
+                failures += 1;
+                writeln!(
+                    tests,
+                    "      <failure type=\"{}\" message=\"{}\">{}</failure>",
+                    rule,
+                    escape(&message),
+                    xml_text(&r.to_string())
+                )
+                .unwrap();
+            } else {
+                writeln!(
+                    tests,
+                    "      <system-out>{}</system-out>",
+                    xml_text(&r.to_string())
+                )
+                .unwrap();
+            }
+            writeln!(tests, "    </testcase>").unwrap();
+        }
+
+        let mut findings = String::new();
+        let mut finding_failures = 0;
+        for f in &self.findings {
+            let name = match (&f.task, &f.resource) {
+                (Some(t), Some(r)) => format!("{} {} {}", f.rule, t, r),
+                (Some(t), None) => format!("{} {}", f.rule, t),
+                (None, Some(r)) => format!("{} {}", f.rule, r),
+                (None, None) => f.rule.clone(),
+            };
+            writeln!(
+                findings,
+                "    <testcase name=\"{}\" classname=\"srp.lint\">",
+                escape(&name)
+            )
+            .unwrap();
+            if f.severity >= Severity::Warning {
+                finding_failures += 1;
+                writeln!(
+                    findings,
+                    "      <failure type=\"{}\" message=\"{}\"/>",
+                    f.severity,
+                    escape(&f.message)
+                )
+                .unwrap();
+            } else {
+                writeln!(
+                    findings,
+                    "      <system-out>{}</system-out>",
+                    xml_text(&f.message)
+                )
+                .unwrap();
+            }
+            writeln!(findings, "    </testcase>").unwrap();
+        }
+
+        let mut s = String::new();
+        writeln!(s, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
+        writeln!(
+            s,
+            "<testsuites tests=\"{}\" failures=\"{}\">",
+            self.results.0.len() + self.findings.len(),
+            failures + finding_failures
+        )
+        .unwrap();
+        writeln!(
+            s,
+            "  <testsuite name=\"schedulability\" tests=\"{}\" failures=\"{}\">",
+            self.results.0.len(),
+            failures
+        )
+        .unwrap();
+        s.push_str(&tests);
+        writeln!(s, "  </testsuite>").unwrap();
+        writeln!(
+            s,
+            "  <testsuite name=\"lint\" tests=\"{}\" failures=\"{}\">",
+            self.findings.len(),
+            finding_failures
+        )
+        .unwrap();
+        s.push_str(&findings);
+        writeln!(s, "  </testsuite>").unwrap();
+        writeln!(s, "</testsuites>").unwrap();
+        s
+    }
+
+    // SARIF 2.1.0 log, one result per failing task and finding
+    pub fn sarif(&self) -> serde_json::Value {
+        let rules: Vec<_> = ANALYSIS_RULES
+            .iter()
+            .chain(VALIDATION_RULES)
+            .copied()
+            .chain(RULES.iter().map(|r| (r.name, r.description)))
+            .map(|(id, description)| json!({ "id": id, "shortDescription": { "text": description } }))
+            .collect();
+        let results: Vec<_> = self
+            .all_findings()
+            .iter()
+            .map(|f| {
+                let mut logical = vec![];
+                if let Some(t) = &f.task {
+                    logical.push(json!({ "name": t, "kind": "task" }));
+                }
+                if let Some(r) = &f.resource {
+                    logical.push(json!({ "name": r, "kind": "resource" }));
+                }
+                json!({
+                    "ruleId": f.rule,
+                    "level": match f.severity {
+                        Severity::Error => "error",
+                        Severity::Warning => "warning",
+                        _ => "note",
+                    },
+                    "message": { "text": f.message },
+                    "locations": [{
+                        "physicalLocation": { "artifactLocation": { "uri": self.source } },
+                        "logicalLocations": logical,
+                    }],
+                })
+            })
+            .collect();
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": { "driver": { "name": "srp", "rules": rules } },
+                "results": results,
+            }],
+        })
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Junit => self.junit(),
+            ReportFormat::Sarif => serde_json::to_string_pretty(&self.sarif()).unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lint::LintConfig;
+
+    #[test]
+    fn report_set1() {
+        let tasks = crate::task_sets::task_set1();
+        let results = tasks.response_time().unwrap();
+        let report = Report {
+            source: "task_set1.json".to_string(),
+            results: &results,
+            findings: tasks.lint(&LintConfig::default()).unwrap(),
+            min_slack: 0.5,
+        };
+
+        let junit = report.junit();
+        assert!(junit.contains("<testsuite name=\"schedulability\" tests=\"3\" failures=\"2\">"));
+        assert!(junit.contains("<failure type=\"unschedulable\" message=\"task T1 may miss"));
+        assert!(junit.contains("blocked by    T2 on R2 (T2 > R1 > R2) length 4"));
+
+        let sarif = report.sarif();
+        let results = sarif["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results[0]["ruleId"], "unschedulable");
+        assert_eq!(results[1]["ruleId"], "insufficient-slack");
+        assert_eq!(
+            results[1]["locations"][0]["logicalLocations"][0]["name"],
+            "T3"
+        );
+    }
+}
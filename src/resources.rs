@@ -0,0 +1,135 @@
+// Resource ceilings and usage
+
+use crate::common::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// A task using a resource
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResourceUser {
+    pub task: String,
+    pub prio: u8,
+    // the longest critical section of the task on the resource
+    pub critical_section: Time,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Resource {
+    pub id: String,
+    // the highest priority of any task using the resource
+    pub ceiling: u8,
+    pub users: Vec<ResourceUser>,
+}
+
+impl Resource {
+    // A resource is effectively lock-free when all its users run at the same
+    // priority (including the case of a single user), as they never preempt
+    // each other
+    pub fn lock_free(&self) -> bool {
+        self.users.iter().all(|u| u.prio == self.ceiling)
+    }
+
+    // The longest critical section over all users
+    pub fn max_critical_section(&self) -> Time {
+        self.users
+            .iter()
+            .map(|u| u.critical_section)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+// The resources of a task set, ordered by id
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResourceTable(pub Vec<Resource>);
+
+impl ResourceTable {
+    pub fn get(&self, id: &str) -> Option<&Resource> {
+        let i = self.0.binary_search_by(|r| r.id.as_str().cmp(id)).ok()?;
+        Some(&self.0[i])
+    }
+
+    pub fn ceiling(&self, id: &str) -> Option<u8> {
+        self.get(id).map(|r| r.ceiling)
+    }
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "resource      {}", self.id)?;
+        writeln!(f, "ceiling       {}", self.ceiling)?;
+        writeln!(f, "lock_free     {}", self.lock_free())?;
+        for u in &self.users {
+            writeln!(
+                f,
+                "user          {} (prio {}) critical section {}",
+                u.task, u.prio, u.critical_section
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ResourceTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Resources")?;
+        for r in &self.0 {
+            writeln!(f, "{}", r)?;
+        }
+        Ok(())
+    }
+}
+
+impl Tasks {
+    // The resource table of the task set
+    pub fn resource_table(&self) -> ResourceTable {
+        let mut resources: BTreeMap<String, Vec<ResourceUser>> = BTreeMap::new();
+        for t in &self.0 {
+            let cs: BTreeMap<String, Time> = t.critical_sections().into_iter().collect();
+            for (r, critical_section) in cs {
+                resources.entry(r).or_default().push(ResourceUser {
+                    task: t.id.clone(),
+                    prio: t.prio,
+                    critical_section,
+                });
+            }
+        }
+        ResourceTable(
+            resources
+                .into_iter()
+                .map(|(id, users)| Resource {
+                    id,
+                    ceiling: users.iter().map(|u| u.prio).max().unwrap_or(0),
+                    users,
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn resource_table_set1() {
+        let table = crate::task_sets::task_set1().resource_table();
+        assert_eq!(table.0.len(), 2);
+
+        let r1 = table.get("R1").unwrap();
+        assert_eq!(r1.ceiling, 2);
+        assert_eq!(r1.users.len(), 1);
+        assert_eq!(r1.max_critical_section(), 10);
+        assert!(r1.lock_free());
+
+        let r2 = table.get("R2").unwrap();
+        assert_eq!(r2.ceiling, 3);
+        assert_eq!(
+            r2.users
+                .iter()
+                .map(|u| (u.task.as_str(), u.critical_section))
+                .collect::<Vec<_>>(),
+            vec![("T2", 4), ("T3", 10)]
+        );
+        assert!(!r2.lock_free());
+    }
+}
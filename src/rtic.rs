@@ -0,0 +1,414 @@
+// Import of task structure from RTIC (v1/v2) application sources
+//
+// The `#[app]` module declares for each task its priority and the shared and
+// local resources it accesses. From this we derive a skeleton task set, with
+// correct priorities and resource usage but without timing information. Tasks
+// spawned by a task, by `spawn = [..]` (v0.5) or `t::spawn(..)` calls in its
+// body, are recorded as its spawns. Software tasks (without `binds`) are run
+// by the `dispatchers = [..]` of the app, the first dispatcher serving the
+// highest software task priority (as RTIC assigns them). The `#[idle]` task
+// runs at priority 0, and blocks tasks by its critical sections.
+
+use crate::common::*;
+use crate::merge::{self, Inconsistency};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AppTask {
+    pub id: String,
+    pub binds: Option<String>,
+    pub prio: u8,
+    pub shared: Vec<String>,
+    pub local: Vec<String>,
+    pub spawns: Vec<String>,
+    // declared message queue capacity of a software task (v1)
+    pub capacity: Option<usize>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct App {
+    pub dispatchers: Vec<String>,
+    pub shared: Vec<String>,
+    pub local: Vec<String>,
+    pub tasks: Vec<AppTask>,
+}
+
+// helper functions
+
+// The source with comments blanked out, keeping offsets (and lines) intact
+fn strip_comments(src: &str) -> String {
+    let bytes = src.as_bytes();
+    let mut out = String::with_capacity(src.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if src[i..].starts_with("//") {
+            while i < bytes.len() && bytes[i] != b'\n' {
+                out.push(' ');
+                i += 1;
+            }
+        } else if src[i..].starts_with("/*") {
+            let end = src[i + 2..].find("*/").map_or(src.len(), |e| i + 2 + e + 2);
+            for c in src[i..end].chars() {
+                out.push(if c == '\n' { '\n' } else { ' ' });
+            }
+            i = end;
+        } else {
+            let c = src[i..].chars().next().unwrap();
+            out.push(c);
+            i += c.len_utf8();
+        }
+    }
+    out
+}
+
+fn line_of(src: &str, offset: usize) -> usize {
+    src[..offset].matches('\n').count() + 1
+}
+
+// The offset just past the delimiter closing the one at open
+fn matching(src: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in src[open..].char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Split at top level commas, i.e., outside of nested delimiters
+fn split_top(s: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            ')' | ']' | '}' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(s[start..].trim());
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
+// The identifier starting s
+fn ident(s: &str) -> &str {
+    let s = s.trim_start();
+    let end = s
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(s.len());
+    &s[..end]
+}
+
+// Resource names of a `[a, &b, c: T = init]` list
+fn resource_list(s: &str) -> Vec<String> {
+    let s = s.trim();
+    let s = s
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .unwrap_or(s);
+    split_top(s)
+        .into_iter()
+        .map(|r| ident(r.trim_start_matches('&')).to_string())
+        .collect()
+}
+
+// Tasks spawned in body, `t::spawn(..)` (or spawn_after, spawn_at)
+fn spawned(body: &str) -> Vec<String> {
+    let mut spawns: Vec<String> = vec![];
+    for (o, _) in body.match_indices("::spawn") {
+        let start = body[..o]
+            .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .map_or(0, |s| s + 1);
+        let id = &body[start..o];
+        if !id.is_empty() && !spawns.iter().any(|s| s == id) {
+            spawns.push(id.to_string());
+        }
+    }
+    spawns
+}
+
+// Field names of the struct following the attribute at offset
+fn struct_fields(src: &str, offset: usize) -> Vec<String> {
+    let open = match src[offset..].find('{') {
+        Some(o) => offset + o,
+        None => return vec![],
+    };
+    let close = matching(src, open).unwrap_or(src.len());
+    let body = &src[open + 1..close - 1];
+    split_top(body)
+        .into_iter()
+        .map(|field| {
+            // skip field attributes, e.g., #[lock_free]
+            let mut field = field;
+            while let Some(rest) = field.strip_prefix("#") {
+                let close = rest.find(']').map_or(rest.len(), |c| c + 1);
+                field = rest[close..].trim_start();
+            }
+            let field = field.strip_prefix("pub ").unwrap_or(field);
+            ident(field).to_string()
+        })
+        .collect()
+}
+
+impl App {
+    // Parse the #[app] module of an RTIC application
+    pub fn parse(src: &str) -> Result<App, ParseError> {
+        let src = strip_comments(src);
+        let mut app = App::default();
+
+        if let Some(o) = src.find("#[rtic::app(").or_else(|| src.find("#[app(")) {
+            let open = o + src[o..].find('(').unwrap();
+            let close = matching(&src, open).ok_or_else(|| {
+                ParseError::new(line_of(&src, o), "unterminated #[app] attribute")
+            })?;
+            for arg in split_top(&src[open + 1..close - 1]) {
+                if let Some(("dispatchers", value)) =
+                    arg.split_once('=').map(|(k, v)| (k.trim(), v))
+                {
+                    app.dispatchers = resource_list(value);
+                }
+            }
+        }
+        if let Some(o) = src.find("#[shared]") {
+            app.shared = struct_fields(&src, o);
+        }
+        if let Some(o) = src.find("#[local]") {
+            app.local = struct_fields(&src, o);
+        }
+
+        // tasks, and idle as a task at priority 0 (below all tasks)
+        let next = |pos: usize| {
+            ["#[task", "#[idle"]
+                .into_iter()
+                .filter_map(|attr| src[pos..].find(attr).map(|o| (pos + o, attr)))
+                .min()
+        };
+        let mut pos = 0;
+        while let Some((start, attr)) = next(pos) {
+            let line = line_of(&src, start);
+            let after = start + attr.len();
+            let mut task = AppTask {
+                prio: if attr == "#[idle" { 0 } else { 1 },
+                ..AppTask::default()
+            };
+            pos = after;
+            if src[after..].starts_with('(') {
+                let close = matching(&src, after).ok_or_else(|| {
+                    ParseError::new(line, format!("unterminated {}] attribute", attr))
+                })?;
+                for arg in split_top(&src[after + 1..close - 1]) {
+                    let (key, value) = arg.split_once('=').ok_or_else(|| {
+                        ParseError::new(line, format!("invalid argument {}", arg))
+                    })?;
+                    let value = value.trim();
+                    match key.trim() {
+                        "binds" => task.binds = Some(value.to_string()),
+                        "priority" => {
+                            task.prio = value.parse().map_err(|_| {
+                                ParseError::new(line, format!("invalid priority {}", value))
+                            })?
+                        }
+                        "shared" => task.shared = resource_list(value),
+                        "local" => task.local = resource_list(value),
+                        "spawn" => task.spawns = resource_list(value),
+                        "capacity" => {
+                            task.capacity = Some(value.parse().map_err(|_| {
+                                ParseError::new(line, format!("invalid capacity {}", value))
+                            })?)
+                        }
+                        _ => {}
+                    }
+                }
+                pos = close;
+            }
+            let f = src[pos..]
+                .find("fn ")
+                .ok_or_else(|| ParseError::new(line, format!("{}] without function", attr)))?;
+            task.id = ident(&src[pos + f + 3..]).to_string();
+            if let Some(open) = src[pos + f..].find('{').map(|o| pos + f + o) {
+                let close = matching(&src, open).unwrap_or(src.len());
+                for s in spawned(&src[open..close]) {
+                    if !task.spawns.contains(&s) {
+                        task.spawns.push(s);
+                    }
+                }
+            }
+            app.tasks.push(task);
+        }
+
+        Ok(app)
+    }
+
+    // The dispatcher of a software task at prio, idle (at priority 0) has none
+    pub fn dispatcher(&self, prio: u8) -> Option<&String> {
+        let mut levels: Vec<u8> = self
+            .tasks
+            .iter()
+            .filter(|t| t.binds.is_none() && t.prio > 0)
+            .map(|t| t.prio)
+            .collect();
+        levels.sort_by(|a, b| b.cmp(a));
+        levels.dedup();
+        let level = levels.iter().position(|p| *p == prio)?;
+        self.dispatchers.get(level)
+    }
+
+    // Skeleton task set, each task holding (empty) critical sections on its shared resources
+    pub fn tasks(&self) -> Tasks {
+        Tasks(
+            self.tasks
+                .iter()
+                .map(|t| Task {
+                    id: t.id.clone(),
+                    prio: t.prio,
+                    trace: Trace {
+                        id: t.id.clone(),
+                        start: 0,
+                        end: 0,
+                        kind: TraceKind::Section,
+                        best: None,
+                        inner: t
+                            .shared
+                            .iter()
+                            .map(|r| Trace {
+                                id: r.clone(),
+                                start: 0,
+                                end: 0,
+                                kind: TraceKind::Section,
+                                best: None,
+                                inner: vec![],
+                            })
+                            .collect(),
+                    },
+                    spawns: t.spawns.clone(),
+                    dispatch: match t.binds {
+                        Some(_) => None,
+                        None => self.dispatcher(t.prio).map(|d| Dispatch {
+                            dispatcher: d.clone(),
+                            capacity: t.capacity.unwrap_or(1),
+                            overhead: 0,
+                        }),
+                    },
+                    ..Task::default()
+                })
+                .collect(),
+        )
+    }
+
+    // Inconsistencies between the application and task set
+    pub fn check(&self, tasks: &Tasks) -> Vec<Inconsistency> {
+        let mut found = vec![];
+        for t in &self.tasks {
+            for r in &t.shared {
+                if !self.shared.is_empty() && !self.shared.contains(r) {
+                    found.push(Inconsistency::UnknownResource(t.id.clone(), r.clone()));
+                }
+            }
+        }
+        found.extend(merge::check(&self.tasks(), tasks));
+        found
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const APP: &str = r#"
+#[rtic::app(device = stm32f4::stm32f411, dispatchers = [EXTI1])]
+mod app {
+    #[shared]
+    struct Shared {
+        r1: u32,
+        #[lock_free]
+        r2: [u8; 4],
+    }
+
+    #[local]
+    struct Local {
+        led: Led,
+    }
+
+    // #[task(priority = 9)]
+    #[task(binds = EXTI0, priority = 2, shared = [r1, &r2], local = [led, cnt: u32 = 0])]
+    fn t2(mut cx: t2::Context) {
+        t1::spawn().ok();
+    }
+
+    #[task(shared = [r1], capacity = 2)]
+    fn t1(mut cx: t1::Context) {}
+
+    #[idle(shared = [r1])]
+    fn idle(mut cx: idle::Context) -> ! {
+        loop {}
+    }
+}
+"#;
+
+    #[test]
+    fn parse_app() {
+        let app = App::parse(APP).unwrap();
+        assert_eq!(app.shared, vec!["r1", "r2"]);
+        assert_eq!(app.local, vec!["led"]);
+        assert_eq!(
+            app.tasks[0],
+            AppTask {
+                id: "t2".to_string(),
+                binds: Some("EXTI0".to_string()),
+                prio: 2,
+                shared: vec!["r1".to_string(), "r2".to_string()],
+                local: vec!["led".to_string(), "cnt".to_string()],
+                spawns: vec!["t1".to_string()],
+                capacity: None,
+            }
+        );
+        assert_eq!(app.tasks[1].id, "t1");
+        assert_eq!(app.tasks[1].prio, 1);
+        assert_eq!(app.tasks[2].id, "idle");
+        assert_eq!(app.tasks[2].prio, 0);
+
+        let tasks = app.tasks();
+        assert_eq!(tasks.0[0].trace.inner.len(), 2);
+        assert_eq!(tasks.0[0].spawns, vec!["t1"]);
+        assert_eq!(tasks.0[0].dispatch, None);
+        assert_eq!(
+            tasks.0[1].dispatch,
+            Some(Dispatch {
+                dispatcher: "EXTI1".to_string(),
+                capacity: 2,
+                overhead: 0,
+            })
+        );
+        assert_eq!(tasks.0[2].trace.inner[0].id, "r1");
+        assert_eq!(tasks.0[2].dispatch, None);
+    }
+
+    #[test]
+    fn check_app() {
+        let app = App::parse(APP).unwrap();
+        let mut tasks = app.tasks();
+        tasks.0[1].prio = 3;
+        tasks.0[1].trace.inner[0].id = "r3".to_string();
+        assert_eq!(
+            app.check(&tasks),
+            vec![
+                Inconsistency::Priority("t1".to_string(), 1, 3),
+                Inconsistency::UndeclaredResource("t1".to_string(), "r3".to_string()),
+                Inconsistency::UnusedResource("t1".to_string(), "r1".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,445 @@
+// Simulation of task sets under SRP scheduling
+//
+// Jobs execute their worst case trace (the longest alternative and every loop
+// iteration), or their best case, locking resources at the ceiling. A pending job is dispatched
+// if its priority exceeds the current (dynamic) priority of the running job,
+// ties among pending jobs are broken by release time and then task order.
+
+use crate::common::*;
+use crate::generate::Rng;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Exec(Time),
+    // resource index
+    Lock(usize),
+    Unlock(usize),
+}
+
+// The observed response times of a task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Observed {
+    pub task: String,
+    pub jobs: usize,
+    pub max_response_time: Time,
+    pub min_response_time: Time,
+    pub deadline_misses: usize,
+}
+
+// The execution of jobs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Execution {
+    Worst,
+    Best,
+    // the worst or best case at random, if an rng is given (otherwise worst)
+    Random,
+}
+
+pub struct Simulation {
+    ids: Vec<String>,
+    prio: Vec<u8>,
+    deadline: Vec<Time>,
+    inter_arrival: Vec<Time>,
+    periodic: Vec<bool>,
+    steps: Vec<Vec<Step>>,
+    best_steps: Vec<Vec<Step>>,
+    ceiling: Vec<u8>,
+    pub execution: Execution,
+}
+
+// A released job
+struct Job {
+    task: usize,
+    release: Time,
+    pc: usize,
+    // left to execute of the current step
+    left: Time,
+    // ceilings of held resources
+    held: Vec<u8>,
+    // executing the best case steps
+    best: bool,
+}
+
+impl Job {
+    fn prio(&self, prio: &[u8]) -> u8 {
+        self.held.iter().fold(prio[self.task], |p, c| p.max(*c))
+    }
+}
+
+// helper functions
+
+// The steps of the body of trace, i.e., own execution time and inner nodes
+// in order of their start. In the best case, own execution time is scaled
+// down to the best case annotation of trace.
+fn body(trace: &Trace, resources: &HashMap<String, usize>, best: bool, steps: &mut Vec<Step>) {
+    let mut inner: Vec<&Trace> = trace.inner.iter().collect();
+    inner.sort_by_key(|t| t.start);
+    // the gaps of own execution before each inner node, and at the end
+    let mut gaps = vec![];
+    let mut at = trace.start;
+    for t in &inner {
+        gaps.push(t.start.saturating_sub(at));
+        at = at.max(t.end);
+    }
+    gaps.push(trace.end.saturating_sub(at));
+
+    let own: Time = gaps.iter().sum();
+    let own_best = match trace.best.filter(|_| best) {
+        Some(b) => b
+            .saturating_sub(inner.iter().map(|t| t.bcet()).sum())
+            .min(own),
+        None => own,
+    };
+    let (mut worst_at, mut best_at) = (0, 0);
+    for (i, gap) in gaps.into_iter().enumerate() {
+        worst_at += gap;
+        let next = (worst_at as u128 * own_best as u128 / own.max(1) as u128) as Time;
+        if next > best_at {
+            steps.push(Step::Exec(next - best_at));
+        }
+        best_at = next;
+        if let Some(t) = inner.get(i) {
+            flatten(t, resources, best, steps);
+        }
+    }
+}
+
+// The steps of the worst (or best) case execution of trace
+fn flatten(trace: &Trace, resources: &HashMap<String, usize>, best: bool, steps: &mut Vec<Step>) {
+    match trace.kind {
+        TraceKind::Section => {
+            let r = resources[&trace.id];
+            steps.push(Step::Lock(r));
+            body(trace, resources, best, steps);
+            steps.push(Step::Unlock(r));
+        }
+        TraceKind::Path => body(trace, resources, best, steps),
+        TraceKind::Alt => {
+            let t = if best {
+                trace.inner.iter().min_by_key(|t| t.bcet())
+            } else {
+                trace.inner.iter().max_by_key(|t| t.wcet())
+            };
+            if let Some(t) = t {
+                flatten(t, resources, best, steps);
+            }
+        }
+        TraceKind::Loop(bound) => {
+            // in the best case, a single iteration if annotated and none otherwise
+            let n = match (best, trace.best) {
+                (false, _) => bound,
+                (true, Some(_)) => 1,
+                (true, None) => 0,
+            };
+            for _ in 0..n {
+                body(trace, resources, best, steps);
+            }
+        }
+    }
+}
+
+impl Simulation {
+    pub fn new(tasks: &Tasks) -> Simulation {
+        let table = tasks.resource_table();
+        let resources: HashMap<String, usize> = table
+            .0
+            .iter()
+            .enumerate()
+            .map(|(i, r)| (r.id.clone(), i))
+            .collect();
+        Simulation {
+            ids: tasks.0.iter().map(|t| t.id.clone()).collect(),
+            prio: tasks.0.iter().map(|t| t.prio).collect(),
+            deadline: tasks.0.iter().map(|t| t.deadline).collect(),
+            inter_arrival: tasks.0.iter().map(|t| t.inter_arrival).collect(),
+            periodic: tasks.0.iter().map(|t| t.periodic).collect(),
+            steps: tasks
+                .0
+                .iter()
+                .map(|t| {
+                    let trace = t.all_traces().max_by_key(|t| t.wcet()).unwrap();
+                    // the dispatcher runs before a software task
+                    let mut steps = vec![];
+                    if t.overhead() > 0 {
+                        steps.push(Step::Exec(t.overhead()));
+                    }
+                    body(trace, &resources, false, &mut steps);
+                    steps
+                })
+                .collect(),
+            best_steps: tasks
+                .0
+                .iter()
+                .map(|t| {
+                    let trace = t.all_traces().min_by_key(|t| t.bcet()).unwrap();
+                    let mut steps = vec![];
+                    if t.overhead() > 0 {
+                        steps.push(Step::Exec(t.overhead()));
+                    }
+                    body(trace, &resources, true, &mut steps);
+                    steps
+                })
+                .collect(),
+            ceiling: table.0.iter().map(|r| r.ceiling).collect(),
+            execution: Execution::Worst,
+        }
+    }
+
+    // Simulate releases until horizon, the first job of each task released at
+    // its offset. Jobs released later still run, interfering until the jobs
+    // released before horizon complete, but are not observed. Subsequent jobs are released inter-arrival apart, delayed by
+    // up to half the inter-arrival at random if rng is given (unless periodic).
+    pub fn run(&self, offsets: &[Time], mut rng: Option<&mut Rng>, horizon: Time) -> Vec<Observed> {
+        let n = self.ids.len();
+        let mut observed: Vec<Observed> = self
+            .ids
+            .iter()
+            .map(|id| Observed {
+                task: id.clone(),
+                jobs: 0,
+                max_response_time: 0,
+                min_response_time: 0,
+                deadline_misses: 0,
+            })
+            .collect();
+        let mut next_release: Vec<Option<Time>> = (0..n)
+            .map(|i| Some(offsets.get(i).copied().unwrap_or(0)))
+            .collect();
+        let mut pending: Vec<Job> = vec![];
+        let mut running: Vec<Job> = vec![];
+        let mut now: Time = 0;
+
+        loop {
+            // release
+            for (i, next_release) in next_release.iter_mut().enumerate() {
+                while let Some(r) = next_release.filter(|r| *r <= now) {
+                    let best = match self.execution {
+                        Execution::Worst => false,
+                        Execution::Best => true,
+                        Execution::Random => rng.as_deref_mut().is_some_and(|r| r.range(0, 1) == 1),
+                    };
+                    pending.push(Job {
+                        task: i,
+                        release: r,
+                        pc: 0,
+                        left: 0,
+                        held: vec![],
+                        best,
+                    });
+                    let mut next = r + self.inter_arrival[i].max(1);
+                    if let Some(rng) = rng.as_deref_mut().filter(|_| !self.periodic[i]) {
+                        next += rng.range(0, self.inter_arrival[i] / 2);
+                    }
+                    *next_release = Some(next);
+                }
+            }
+
+            // jobs released from horizon on only interfere with the observed ones
+            let observing = pending.iter().chain(&running).any(|j| j.release < horizon);
+            if !observing && next_release.iter().flatten().all(|r| *r >= horizon) {
+                break;
+            }
+
+            // dispatch
+            let current = running.last().map(|j| j.prio(&self.prio));
+            let next = (0..pending.len())
+                .filter(|j| current.is_none_or(|c| self.prio[pending[*j].task] > c))
+                .min_by_key(|j| {
+                    let job = &pending[*j];
+                    (
+                        std::cmp::Reverse(self.prio[job.task]),
+                        job.release,
+                        job.task,
+                    )
+                });
+            if let Some(j) = next {
+                let mut job = pending.remove(j);
+                job.left = self.step_time(&job);
+                running.push(job);
+                continue;
+            }
+
+            let release = next_release.iter().flatten().min().copied();
+            let Some(job) = running.last_mut() else {
+                match release {
+                    Some(r) => {
+                        now = r;
+                        continue;
+                    }
+                    None => break,
+                }
+            };
+
+            // execute the running job, until its step completes or the next release
+            match self.steps(job).get(job.pc) {
+                Some(Step::Exec(_)) => {
+                    let until = release.map_or(now + job.left, |r| r.min(now + job.left));
+                    job.left -= until - now;
+                    now = until;
+                    if job.left == 0 {
+                        job.pc += 1;
+                        job.left = self.step_time(job);
+                    }
+                }
+                Some(Step::Lock(r)) => {
+                    job.held.push(self.ceiling[*r]);
+                    job.pc += 1;
+                    job.left = self.step_time(job);
+                }
+                Some(Step::Unlock(_)) => {
+                    job.held.pop();
+                    job.pc += 1;
+                    job.left = self.step_time(job);
+                }
+                None => {
+                    let job = running.pop().unwrap();
+                    if job.release >= horizon {
+                        continue;
+                    }
+                    let response_time = now - job.release;
+                    let o = &mut observed[job.task];
+                    o.min_response_time = match o.jobs {
+                        0 => response_time,
+                        _ => o.min_response_time.min(response_time),
+                    };
+                    o.jobs += 1;
+                    o.max_response_time = o.max_response_time.max(response_time);
+                    if response_time > self.deadline[job.task] {
+                        o.deadline_misses += 1;
+                    }
+                }
+            }
+        }
+        observed
+    }
+
+    fn steps(&self, job: &Job) -> &[Step] {
+        if job.best {
+            &self.best_steps[job.task]
+        } else {
+            &self.steps[job.task]
+        }
+    }
+
+    fn step_time(&self, job: &Job) -> Time {
+        match self.steps(job).get(job.pc) {
+            Some(Step::Exec(t)) => *t,
+            _ => 0,
+        }
+    }
+
+    // Offsets placing each lower priority task such that it holds the longest
+    // critical section blocking task t when t is released at time start.
+    pub fn blocking_offsets(&self, t: usize, start: Time) -> Vec<Time> {
+        let mut offsets = vec![start; self.ids.len()];
+        let mut best: Option<(Time, usize, Time)> = None;
+        for (i, steps) in self.steps.iter().enumerate() {
+            if self.prio[i] >= self.prio[t] {
+                continue;
+            }
+            // the time into the job at which each blocking lock is taken
+            let mut at = 0;
+            let mut locked: Vec<(usize, Time)> = vec![];
+            for step in steps {
+                match step {
+                    Step::Exec(d) => at += d,
+                    Step::Lock(r) => locked.push((*r, at)),
+                    Step::Unlock(r) => {
+                        let (_, from) = locked.pop().unwrap();
+                        if self.ceiling[*r] >= self.prio[t]
+                            && best.is_none_or(|(len, _, _)| at - from > len)
+                        {
+                            best = Some((at - from, i, from));
+                        }
+                    }
+                }
+            }
+        }
+        if let Some((_, i, from)) = best {
+            // released such that the lock is taken just before t is released
+            offsets[i] = start - (from + 1).min(start);
+        }
+        offsets
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn simulate_set2() {
+        let tasks = Tasks::load(&PathBuf::from("task_sets/task_set2.json")).unwrap();
+        let sim = Simulation::new(&tasks);
+        let observed = sim.run(&[0, 0, 0], None, 100);
+        let rt: Vec<Time> = observed.iter().map(|o| o.max_response_time).collect();
+        // synchronous release, no blocking
+        assert_eq!(rt, vec![70, 40, 10]);
+        assert!(observed.iter().all(|o| o.deadline_misses == 0));
+
+        // T1 holds R3 when T3 is released
+        let offsets = sim.blocking_offsets(2, 50);
+        let observed = sim.run(&offsets, None, 200);
+        assert!(observed[2].max_response_time > 10);
+        assert!(observed[2].max_response_time <= 20);
+    }
+
+    #[test]
+    fn simulate_best_case() {
+        let mut tasks = Tasks::load(&PathBuf::from("task_sets/task_set2.json")).unwrap();
+        tasks.0[0].trace.best = Some(14);
+        tasks.0[1].trace.best = Some(20);
+        let mut sim = Simulation::new(&tasks);
+        sim.execution = Execution::Best;
+        let observed = sim.run(&[0, 0, 0], None, 100);
+        let rt: Vec<Time> = observed.iter().map(|o| o.min_response_time).collect();
+        // T3, then T2 and T1 in their best cases
+        assert_eq!(rt, vec![44, 30, 10]);
+
+        let results = tasks.response_time().unwrap();
+        for (o, r) in observed.iter().zip(&results.0) {
+            assert!(r.best_response_time <= o.min_response_time);
+        }
+    }
+
+    #[test]
+    fn best_case_random_releases() {
+        use crate::generate::GenConfig;
+        let config = GenConfig {
+            tasks: 5,
+            utilization: 0.8,
+            ..GenConfig::default()
+        };
+        // sporadic tasks may be released late, and then preempt nothing,
+        // while periodic tasks preempt at least as the best case assumes
+        for periodic in [false, true] {
+            let mut preempted = false;
+            for seed in 0..20 {
+                let mut rng = Rng::new(seed);
+                let mut tasks = config.generate(&mut rng).unwrap();
+                for t in tasks.0.iter_mut() {
+                    t.periodic = periodic;
+                }
+                let results = tasks.response_time().unwrap();
+                preempted |= results.0.iter().any(|r| r.best_response_time > r.bcet);
+                let mut sim = Simulation::new(&tasks);
+                sim.execution = Execution::Random;
+                let horizon = 4 * tasks.0.iter().map(|t| t.inter_arrival).max().unwrap();
+                for _ in 0..5 {
+                    let offsets: Vec<Time> = tasks
+                        .0
+                        .iter()
+                        .map(|t| rng.range(0, t.inter_arrival))
+                        .collect();
+                    let observed = sim.run(&offsets, Some(&mut rng), horizon);
+                    for (o, r) in observed.iter().zip(&results.0) {
+                        assert!(o.jobs == 0 || r.best_response_time <= o.min_response_time);
+                    }
+                }
+            }
+            assert_eq!(preempted, periodic);
+        }
+    }
+}
@@ -16,8 +16,15 @@ pub fn task_set1() -> Tasks {
             id: "T1".to_string(),
             start: 0,
             end: 10,
+            kind: TraceKind::Section,
+            best: None,
             inner: vec![],
         },
+        traces: vec![],
+        time_base: TimeBase::default(),
+        spawns: vec![],
+        periodic: false,
+        dispatch: None,
     };
 
     // Task T2
@@ -32,15 +39,21 @@ pub fn task_set1() -> Tasks {
             id: "T2".to_string(),
             start: 0,
             end: 30,
+            kind: TraceKind::Section,
+            best: None,
             inner: vec![
                 Trace {
                     id: "R1".to_string(),
                     start: 10,
                     end: 20,
+                    kind: TraceKind::Section,
+                    best: None,
                     inner: vec![Trace {
                         id: "R2".to_string(),
                         start: 12,
                         end: 16,
+                        kind: TraceKind::Section,
+                        best: None,
                         inner: vec![],
                     }],
                 },
@@ -48,10 +61,17 @@ pub fn task_set1() -> Tasks {
                     id: "R1".to_string(),
                     start: 22,
                     end: 28,
+                    kind: TraceKind::Section,
+                    best: None,
                     inner: vec![],
                 },
             ],
         },
+        traces: vec![],
+        time_base: TimeBase::default(),
+        spawns: vec![],
+        periodic: false,
+        dispatch: None,
     };
 
     // Task T3
@@ -64,13 +84,22 @@ pub fn task_set1() -> Tasks {
             id: "T3".to_string(),
             start: 0,
             end: 30,
+            kind: TraceKind::Section,
+            best: None,
             inner: vec![Trace {
                 id: "R2".to_string(),
                 start: 10,
                 end: 20,
+                kind: TraceKind::Section,
+                best: None,
                 inner: vec![],
             }],
         },
+        traces: vec![],
+        time_base: TimeBase::default(),
+        spawns: vec![],
+        periodic: false,
+        dispatch: None,
     };
 
     Tasks(vec![t1, t2, t3])
@@ -0,0 +1,410 @@
+// Time units and exact conversion between time bases
+
+use crate::common::*;
+use std::fmt;
+use std::str::FromStr;
+
+impl TimeUnit {
+    // The length of one tick in seconds, as a fraction (numerator, denominator)
+    fn seconds(&self, clock_hz: Option<u64>) -> Option<(u128, u128)> {
+        match self {
+            TimeUnit::Cycles => clock_hz.map(|hz| (1, hz as u128)),
+            TimeUnit::Ns => Some((1, 1_000_000_000)),
+            TimeUnit::Us => Some((1, 1_000_000)),
+            TimeUnit::Ms => Some((1, 1_000)),
+            TimeUnit::S => Some((1, 1)),
+        }
+    }
+}
+
+impl fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            TimeUnit::Cycles => "cycles",
+            TimeUnit::Ns => "ns",
+            TimeUnit::Us => "us",
+            TimeUnit::Ms => "ms",
+            TimeUnit::S => "s",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for TimeUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cycles" => Ok(TimeUnit::Cycles),
+            "ns" => Ok(TimeUnit::Ns),
+            "us" => Ok(TimeUnit::Us),
+            "ms" => Ok(TimeUnit::Ms),
+            "s" => Ok(TimeUnit::S),
+            _ => Err(format!("unknown time unit {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for TimeBase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.clock_hz {
+            Some(hz) if self.unit == TimeUnit::Cycles => write!(f, "{} @ {} Hz", self.unit, hz),
+            _ => write!(f, "{}", self.unit),
+        }
+    }
+}
+
+impl TimeBase {
+    pub fn is_default(&self) -> bool {
+        *self == TimeBase::default()
+    }
+
+    // The scaling factor (numerator, denominator) from self to time base to
+    fn factor(&self, to: &TimeBase, id: &str) -> Result<(u128, u128), AnalysisError> {
+        if self.unit == to.unit && (self.unit != TimeUnit::Cycles || self.clock_hz == to.clock_hz) {
+            return Ok((1, 1));
+        }
+        let missing = || AnalysisError::MissingClock(id.to_string());
+        let (from_num, from_den) = self.unit.seconds(self.clock_hz).ok_or_else(missing)?;
+        let (to_num, to_den) = to.unit.seconds(to.clock_hz).ok_or_else(missing)?;
+        Ok((from_num * to_den, from_den * to_num))
+    }
+
+    // Exact conversion of value from self to time base to, lossy conversions are rejected
+    pub fn convert(&self, value: Time, to: &TimeBase, id: &str) -> Result<Time, AnalysisError> {
+        let (num, den) = self.factor(to, id)?;
+        let scaled = value as u128 * num;
+        if !scaled.is_multiple_of(den) {
+            return Err(AnalysisError::LossyConversion(id.to_string(), value));
+        }
+        Time::try_from(scaled / den).map_err(|_| AnalysisError::Overflow(id.to_string()))
+    }
+
+    // Conversion of value from self to time base to, rounding up (safe for upper bounds)
+    pub fn convert_ceil(
+        &self,
+        value: Time,
+        to: &TimeBase,
+        id: &str,
+    ) -> Result<Time, AnalysisError> {
+        let (num, den) = self.factor(to, id)?;
+        let scaled = (value as u128 * num).div_ceil(den);
+        Time::try_from(scaled).map_err(|_| AnalysisError::Overflow(id.to_string()))
+    }
+
+    // Conversion of value from self to time base to, rounding down (safe for lower bounds)
+    pub fn convert_floor(
+        &self,
+        value: Time,
+        to: &TimeBase,
+        id: &str,
+    ) -> Result<Time, AnalysisError> {
+        let (num, den) = self.factor(to, id)?;
+        let scaled = value as u128 * num / den;
+        Time::try_from(scaled).map_err(|_| AnalysisError::Overflow(id.to_string()))
+    }
+
+    // Duration of a single tick in seconds, used to order time bases by resolution
+    fn resolution(&self) -> Option<f64> {
+        self.unit
+            .seconds(self.clock_hz)
+            .map(|(num, den)| num as f64 / den as f64)
+    }
+}
+
+// helper functions
+// The trace with start times (and best cases) converted by floor and end times by ceil
+fn convert_trace(
+    trace: &Trace,
+    floor: &impl Fn(Time) -> Result<Time, AnalysisError>,
+    ceil: &impl Fn(Time) -> Result<Time, AnalysisError>,
+) -> Result<Trace, AnalysisError> {
+    Ok(Trace {
+        id: trace.id.clone(),
+        start: floor(trace.start)?,
+        end: ceil(trace.end)?,
+        kind: trace.kind,
+        best: trace.best.map(floor).transpose()?,
+        inner: trace
+            .inner
+            .iter()
+            .map(|t| convert_trace(t, floor, ceil))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+impl Task {
+    // The task with its timing values converted by floor (lower bounds) and ceil
+    // (upper bounds)
+    fn convert_with(
+        &self,
+        to: &TimeBase,
+        floor: impl Fn(Time) -> Result<Time, AnalysisError>,
+        ceil: impl Fn(Time) -> Result<Time, AnalysisError>,
+    ) -> Result<Task, AnalysisError> {
+        Ok(Task {
+            id: self.id.clone(),
+            prio: self.prio,
+            deadline: ceil(self.deadline)?,
+            inter_arrival: ceil(self.inter_arrival)?,
+            trace: convert_trace(&self.trace, &floor, &ceil)?,
+            traces: self
+                .traces
+                .iter()
+                .map(|t| convert_trace(t, &floor, &ceil))
+                .collect::<Result<_, _>>()?,
+            time_base: *to,
+            spawns: self.spawns.clone(),
+            periodic: self.periodic,
+            dispatch: self
+                .dispatch
+                .as_ref()
+                .map(|d| {
+                    Ok::<_, AnalysisError>(Dispatch {
+                        overhead: ceil(d.overhead)?,
+                        ..d.clone()
+                    })
+                })
+                .transpose()?,
+        })
+    }
+
+    // The task with all timing values converted to time base to
+    pub fn normalize(&self, to: &TimeBase) -> Result<Task, AnalysisError> {
+        let convert = |v: Time| self.time_base.convert(v, to, &self.id);
+        self.convert_with(to, convert, convert)
+    }
+
+    // The task with all timing values in time base to, rounding times outwards
+    pub fn convert(&self, to: &TimeBase) -> Result<Task, AnalysisError> {
+        self.convert_with(
+            to,
+            |v| self.time_base.convert_floor(v, to, &self.id),
+            |v| self.time_base.convert_ceil(v, to, &self.id),
+        )
+    }
+}
+
+impl Tasks {
+    // The clock frequency of the task set, shared by all tasks given in cycles
+    pub fn clock_hz(&self) -> Result<Option<u64>, AnalysisError> {
+        let mut clock_hz = None;
+        for t in &self.0 {
+            if let Some(hz) = t.time_base.clock_hz {
+                match clock_hz {
+                    Some(other) if other != hz => {
+                        return Err(AnalysisError::ClockMismatch(other, hz))
+                    }
+                    _ => clock_hz = Some(hz),
+                }
+            }
+        }
+        Ok(clock_hz)
+    }
+
+    // The time base of each task, with the clock frequency of the task set filled in
+    fn time_bases(&self) -> Result<Vec<TimeBase>, AnalysisError> {
+        let clock_hz = self.clock_hz()?;
+        Ok(self
+            .0
+            .iter()
+            .map(|t| match t.time_base {
+                TimeBase {
+                    unit: TimeUnit::Cycles,
+                    clock_hz: None,
+                } => TimeBase {
+                    unit: TimeUnit::Cycles,
+                    clock_hz,
+                },
+                tb => tb,
+            })
+            .collect())
+    }
+
+    // The common time base of the task set, i.e., the finest time base in use
+    pub fn common_time_base(&self) -> Result<TimeBase, AnalysisError> {
+        let time_bases = self.time_bases()?;
+        let mut common: Option<(TimeBase, &str)> = None;
+        for (t, tb) in self.0.iter().zip(time_bases) {
+            common = match common {
+                None => Some((tb, &t.id)),
+                Some((c, _)) if c == tb => common,
+                Some((c, c_id)) => {
+                    let res = tb
+                        .resolution()
+                        .ok_or_else(|| AnalysisError::MissingClock(t.id.clone()))?;
+                    let c_res = c
+                        .resolution()
+                        .ok_or_else(|| AnalysisError::MissingClock(c_id.to_string()))?;
+                    if res < c_res {
+                        Some((tb, &t.id))
+                    } else {
+                        common
+                    }
+                }
+            };
+        }
+        Ok(common.map_or_else(TimeBase::default, |(c, _)| c))
+    }
+
+    // The task set with all tasks converted to the common time base
+    pub fn normalize(&self) -> Result<Tasks, AnalysisError> {
+        let to = self.common_time_base()?;
+        Ok(Tasks(
+            self.0
+                .iter()
+                .zip(self.time_bases()?)
+                .map(|(t, time_base)| {
+                    Task {
+                        time_base,
+                        ..t.clone()
+                    }
+                    .normalize(&to)
+                })
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+impl TaskResult {
+    // The result with all timing values in time base to, rounded up
+    pub fn convert(&self, to: &TimeBase) -> Result<TaskResult, AnalysisError> {
+        let from = &self.task.time_base;
+        let id = &self.task.id;
+        let convert = |v: Time| from.convert_ceil(v, to, id);
+        let convert_floor = |v: Time| from.convert_floor(v, to, id);
+        let task = self.task.convert(to)?;
+        let response_time = self.response_time.map(convert).transpose()?;
+        let best_response_time = convert_floor(self.best_response_time)?;
+        Ok(TaskResult {
+            task,
+            response_time,
+            wcet: convert(self.wcet)?,
+            blocking: convert(self.blocking)?,
+            blocker: self
+                .blocker
+                .as_ref()
+                .map(|b| {
+                    Ok::<_, AnalysisError>(Blocker {
+                        length: convert(b.length)?,
+                        ..b.clone()
+                    })
+                })
+                .transpose()?,
+            interference: self.interference.map(convert).transpose()?,
+            interferers: self
+                .interferers
+                .iter()
+                .map(|i| {
+                    Ok(Interferer {
+                        cost: convert(i.cost)?,
+                        ..i.clone()
+                    })
+                })
+                .collect::<Result<_, AnalysisError>>()?,
+            release_jitter: convert(self.release_jitter)?,
+            required_capacity: self.required_capacity,
+            bcet: convert_floor(self.bcet)?,
+            best_response_time,
+            jitter: response_time.map(|r| r.saturating_sub(best_response_time)),
+            iterations: self
+                .iterations
+                .iter()
+                .map(|i| {
+                    Ok(Iteration {
+                        job: i.job,
+                        busy_period: convert(i.busy_period)?,
+                        next: convert(i.next)?,
+                    })
+                })
+                .collect::<Result<_, AnalysisError>>()?,
+        })
+    }
+}
+
+impl TasksResult {
+    // The results with all timing values in time base to, rounded up
+    pub fn convert(&self, to: &TimeBase) -> Result<TasksResult, AnalysisError> {
+        Ok(TasksResult(
+            self.0
+                .iter()
+                .map(|r| r.convert(to))
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::common::*;
+
+    fn task(id: &str, end: Time, time_base: TimeBase) -> Task {
+        Task {
+            id: id.to_string(),
+            prio: 1,
+            deadline: 100,
+            inter_arrival: 100,
+            trace: Trace {
+                id: id.to_string(),
+                start: 0,
+                end,
+                kind: TraceKind::Section,
+                best: None,
+                inner: vec![],
+            },
+            traces: vec![],
+            time_base,
+            spawns: vec![],
+            periodic: false,
+            dispatch: None,
+        }
+    }
+
+    #[test]
+    fn normalize_cycles_and_us() {
+        let cycles = TimeBase {
+            unit: TimeUnit::Cycles,
+            clock_hz: Some(200_000_000),
+        };
+        let us = TimeBase {
+            unit: TimeUnit::Us,
+            clock_hz: None,
+        };
+        let tasks = Tasks(vec![task("T1", 50, cycles), task("T2", 2, us)]);
+        let normalized = tasks.normalize().unwrap();
+        assert_eq!(normalized.0[0].time_base, cycles);
+        assert_eq!(normalized.0[1].trace.end, 400);
+        assert_eq!(normalized.0[1].deadline, 20_000);
+    }
+
+    #[test]
+    fn lossy_conversion() {
+        let ns = TimeBase {
+            unit: TimeUnit::Ns,
+            clock_hz: None,
+        };
+        let us = TimeBase {
+            unit: TimeUnit::Us,
+            clock_hz: None,
+        };
+        assert_eq!(
+            ns.convert(1500, &us, "T1"),
+            Err(AnalysisError::LossyConversion("T1".to_string(), 1500))
+        );
+        assert_eq!(ns.convert_ceil(1500, &us, "T1"), Ok(2));
+
+        let tasks = Tasks(vec![task("T1", 50, TimeBase::default()), task("T2", 2, us)]);
+        assert_eq!(
+            tasks.normalize(),
+            Err(AnalysisError::MissingClock("T1".to_string()))
+        );
+
+        // results are rounded outwards, including the traces of the task
+        let mut results = Tasks(vec![task("T1", 1500, ns)]).response_time().unwrap();
+        results.0[0].task.trace.start = 700;
+        let converted = results.convert(&us).unwrap();
+        let t1 = &converted.0[0].task;
+        assert_eq!((t1.trace.start, t1.trace.end), (0, 2));
+        assert_eq!((t1.deadline, t1.time_base), (1, us));
+    }
+}